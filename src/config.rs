@@ -0,0 +1,472 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::tui::components::BorderType;
+use crate::tui::layout::Align;
+use crate::tui::{Keymap, Theme};
+
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/greetd/greetly.toml";
+const DEFAULT_STATE_PATH: &str = "/var/cache/greetly/state";
+const DEFAULT_SOCKET_TIMEOUT: u64 = 120;
+
+const MIN_FORM_WIDTH: u16 = 24;
+const MIN_FORM_HEIGHT: u16 = 10;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "Cannot read {}: {}", path.display(), err),
+            Self::Syntax {
+                path,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "{} (line {}, column {}): {}",
+                path.display(),
+                line,
+                column,
+                message
+            ),
+            Self::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl ConfigError {
+    pub(crate) fn from_toml(path: &Path, err: toml::de::Error) -> Self {
+        let message = err.to_string();
+        match err.line_col() {
+            Some((line, column)) => {
+                // toml appends the location to its message, we report it separately
+                let suffix = format!(" at line {} column {}", line + 1, column + 1);
+                let message = message.trim_end_matches(suffix.as_str()).to_owned();
+
+                Self::Syntax {
+                    path: path.to_owned(),
+                    line: line + 1,
+                    column: column + 1,
+                    message,
+                }
+            }
+            None => Self::Invalid(format!("{}: {}", path.display(), message)),
+        }
+    }
+}
+
+/// Settings for the session started after a successful login.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// Command started for the authenticated user (default: `/bin/bash`).
+    pub command: String,
+    /// Command prepended to the session command, e.g. `dbus-run-session`
+    /// (default: none).
+    pub wrapper: Option<String>,
+    /// Command prepended to X11 sessions (default: `startx /usr/bin/env`).
+    pub xsession_wrapper: Option<String>,
+    /// Directories searched for wayland session files
+    /// (default: `/usr/share/wayland-sessions`).
+    pub wayland_sessions: Vec<PathBuf>,
+    /// Directories searched for X11 session files
+    /// (default: `/usr/share/xsessions`).
+    pub x11_sessions: Vec<PathBuf>,
+    /// Id of the preselected session, either the name of the session file
+    /// without `.desktop` or `command` (default: the first one found).
+    pub default: Option<String>,
+    /// Preselect the session a user started last time (default: `true`).
+    pub remember: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            command: "/bin/bash".to_owned(),
+            wrapper: None,
+            xsession_wrapper: Some("startx /usr/bin/env".to_owned()),
+            wayland_sessions: vec![PathBuf::from("/usr/share/wayland-sessions")],
+            x11_sessions: vec![PathBuf::from("/usr/share/xsessions")],
+            default: None,
+            remember: true,
+        }
+    }
+}
+
+/// Settings for the user logging in.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserConfig {
+    /// User asked for the password right away (default: none).
+    pub default: Option<String>,
+    /// Prefer the last successfully authenticated user over `default`
+    /// (default: `false`).
+    pub remember: bool,
+    /// File the known users are read from (default: `/etc/passwd`).
+    pub passwd_file: PathBuf,
+    /// File `UID_MIN` and `UID_MAX` are read from (default: `/etc/login.defs`).
+    pub login_defs: PathBuf,
+    /// Lowest UID of a listed user (default: from `login_defs` or 1000).
+    pub uid_min: Option<u32>,
+    /// Highest UID of a listed user (default: from `login_defs` or 60000).
+    pub uid_max: Option<u32>,
+    /// Users never listed (default: none).
+    pub hidden: Vec<String>,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        Self {
+            default: None,
+            remember: false,
+            passwd_file: PathBuf::from("/etc/passwd"),
+            login_defs: PathBuf::from("/etc/login.defs"),
+            uid_min: None,
+            uid_max: None,
+            hidden: Vec::new(),
+        }
+    }
+}
+
+/// Appearance of the centered login form.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormConfig {
+    /// Outer width of the form in columns (default: 64).
+    pub width: u16,
+    /// Outer height of the form in rows (default: 11).
+    pub height: u16,
+    /// Prompt shown while asking for the username (default: `Login:`).
+    pub prompt: String,
+    /// Banner in the top border of the form (default: the host name).
+    pub title: Option<String>,
+    /// Alignment of the banner: `left`, `center` or `right` (default: `center`).
+    pub title_align: Align,
+}
+
+impl Default for FormConfig {
+    fn default() -> Self {
+        Self {
+            width: 64,
+            height: 11,
+            prompt: "Login:".to_owned(),
+            title: None,
+            title_align: Align::Center,
+        }
+    }
+}
+
+/// Border of each kind of box, replaced by `ascii` if the locale is not UTF-8.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BorderConfig {
+    /// Login form and the user list below it (default: `unicode`).
+    pub form: BorderType,
+    /// Info and error messages (default: `unicode`).
+    pub message: BorderType,
+    /// Confirmation dialogs (default: `unicode`).
+    pub dialog: BorderType,
+}
+
+impl Default for BorderConfig {
+    fn default() -> Self {
+        Self {
+            form: BorderType::Unicode,
+            message: BorderType::Unicode,
+            dialog: BorderType::Unicode,
+        }
+    }
+}
+
+impl BorderConfig {
+    /// The borders usable without UTF-8 if `utf8` is false.
+    pub fn fallback(&self, utf8: bool) -> Self {
+        Self {
+            form: self.form.fallback(utf8),
+            message: self.message.fallback(utf8),
+            dialog: self.dialog.fallback(utf8),
+        }
+    }
+}
+
+/// A full-width part of the screen.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenRow {
+    /// Keys of the power actions.
+    Keys,
+    /// The form title or host name, left out of the form border then.
+    Banner,
+    /// Date and time.
+    Clock,
+    /// Status texts and messages without room for a box.
+    Status,
+    /// The rest of the screen, holding the login form and the messages.
+    Form,
+}
+
+/// Place of the login form in its part of the screen.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormPlacement {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl FormPlacement {
+    /// Horizontal and vertical alignment of the form.
+    pub fn align(self) -> (Align, Align) {
+        match self {
+            Self::Center => (Align::Center, Align::Center),
+            Self::Top => (Align::Center, Align::Start),
+            Self::Bottom => (Align::Center, Align::End),
+            Self::Left => (Align::Start, Align::Center),
+            Self::Right => (Align::End, Align::Center),
+        }
+    }
+}
+
+/// Which parts make up the screen and where they go.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScreenConfig {
+    /// Rows from top to bottom, `form` exactly once
+    /// (default: `keys`, `form`, `status`).
+    pub rows: Vec<ScreenRow>,
+    /// Place of the login form (default: `center`).
+    pub form: FormPlacement,
+    /// Show messages as boxes next to the form instead of in the status line
+    /// (default: `true`).
+    pub messages: bool,
+    /// Format of the clock, see `strftime(3)` (default: `%a %d %b %H:%M`).
+    pub clock_format: String,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        Self {
+            rows: vec![ScreenRow::Keys, ScreenRow::Form, ScreenRow::Status],
+            form: FormPlacement::Center,
+            messages: true,
+            clock_format: "%a %d %b %H:%M".to_owned(),
+        }
+    }
+}
+
+/// Commands run for the power actions, an empty command disables the action.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerConfig {
+    /// Shut down the system (default: `systemctl poweroff`).
+    pub poweroff: Option<String>,
+    /// Reboot the system (default: `systemctl reboot`).
+    pub reboot: Option<String>,
+    /// Suspend the system (default: `systemctl suspend`).
+    pub suspend: Option<String>,
+    /// Hibernate the system (default: `systemctl hibernate`).
+    pub hibernate: Option<String>,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            poweroff: Some("systemctl poweroff".to_owned()),
+            reboot: Some("systemctl reboot".to_owned()),
+            suspend: Some("systemctl suspend".to_owned()),
+            hibernate: Some("systemctl hibernate".to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// File keeping data between runs (default: `/var/cache/greetly/state`).
+    pub state_file: PathBuf,
+    /// greetd socket, overrides `GREETD_SOCK` (default: none).
+    pub socket: Option<PathBuf>,
+    /// Seconds to wait for greetd to answer, 0 waits forever (default: 120).
+    pub socket_timeout: u64,
+    pub user: UserConfig,
+    pub session: SessionConfig,
+    pub form: FormConfig,
+    pub power: PowerConfig,
+    pub keys: Keymap,
+    pub theme: Theme,
+    pub borders: BorderConfig,
+    pub screen: ScreenConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            state_file: PathBuf::from(DEFAULT_STATE_PATH),
+            socket: None,
+            socket_timeout: DEFAULT_SOCKET_TIMEOUT,
+            user: UserConfig::default(),
+            session: SessionConfig::default(),
+            form: FormConfig::default(),
+            power: PowerConfig::default(),
+            keys: Keymap::default(),
+            theme: Theme::default(),
+            borders: BorderConfig::default(),
+            screen: ScreenConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration from `path`.
+    ///
+    /// A missing file is only accepted for the default location, in which case
+    /// the built-in defaults are used.
+    pub fn load(path: Option<&str>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => Self::read(Path::new(path), true),
+            None => Self::read(Path::new(DEFAULT_CONFIG_PATH), false),
+        }
+    }
+
+    /// Load the configuration from `path`, falling back to the defaults if it
+    /// is missing and not `required`.
+    fn read(path: &Path, required: bool) -> Result<Self, ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default());
+            }
+            Err(err) => return Err(ConfigError::Io(path.to_owned(), err)),
+        };
+
+        let config: Self =
+            toml::from_str(&content).map_err(|err| ConfigError::from_toml(path, err))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Time to wait for greetd to answer, `None` waits forever.
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.socket_timeout {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.session.command.trim().is_empty() {
+            return Err(ConfigError::Invalid(
+                "session.command must not be empty".to_owned(),
+            ));
+        }
+        if self.form.width < MIN_FORM_WIDTH || self.form.height < MIN_FORM_HEIGHT {
+            return Err(ConfigError::Invalid(format!(
+                "form must be at least {}x{} characters",
+                MIN_FORM_WIDTH, MIN_FORM_HEIGHT
+            )));
+        }
+        let forms = self
+            .screen
+            .rows
+            .iter()
+            .filter(|&&row| row == ScreenRow::Form)
+            .count();
+        if forms != 1 {
+            return Err(ConfigError::Invalid(
+                "screen.rows must contain \"form\" exactly once".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    /// Load `content` from a temporary file.
+    fn parse(name: &str, content: &str) -> Result<Config, ConfigError> {
+        let path =
+            env::temp_dir().join(format!("greetly-test-{}-{}.toml", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        let config = Config::read(&path, true);
+        let _ = fs::remove_file(&path);
+        config
+    }
+
+    #[test]
+    fn reports_syntax_error_location() {
+        match parse(
+            "syntax",
+            "[session]\ncommand = \"sh\"\n\n[form]\nwidth = = 3\n",
+        ) {
+            Err(ConfigError::Syntax { line, column, .. }) => assert_eq!((line, column), (5, 9)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        match parse("unknown", "[form]\nwidht = 40\n") {
+            Err(ConfigError::Syntax { message, .. }) => {
+                assert!(message.contains("widht"), "{}", message)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn only_the_default_file_may_be_missing() {
+        let path = env::temp_dir().join("greetly-test-missing.toml");
+        let config = Config::read(&path, false).unwrap();
+        assert_eq!(config.form.width, FormConfig::default().width);
+        assert!(matches!(
+            Config::read(&path, true),
+            Err(ConfigError::Io(_, ref err)) if err.kind() == io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn validates_values() {
+        let invalid = |name, content| match parse(name, content) {
+            Err(ConfigError::Invalid(message)) => message,
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        assert!(invalid("command", "[session]\ncommand = \" \"\n").contains("session.command"));
+        assert!(invalid("form", "[form]\nwidth = 23\n").contains("form must be"));
+        assert!(invalid("height", "[form]\nheight = 9\n").contains("form must be"));
+        assert!(invalid("rows", "[screen]\nrows = [\"form\", \"form\"]\n").contains("screen.rows"));
+        assert!(parse("valid", "[form]\nwidth = 24\nheight = 10\n").is_ok());
+    }
+}
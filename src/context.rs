@@ -8,8 +8,15 @@
 
 use greetd_ipc as greetd;
 use std::env;
+use std::fmt;
+use std::fs;
 use std::io;
+use std::io::{Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use greetd::codec::SyncCodec;
 
@@ -18,7 +25,7 @@ use crate::tui::GreetUI;
 const ENV_SOCKET: &str = "GREETD_SOCK";
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-enum ContextState {
+pub enum ContextState {
     Connected,
     Created,
     Authenticated,
@@ -26,12 +33,75 @@ enum ContextState {
     Failed,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Operation {
+    CreateSession,
+    AuthenticationResponse,
+    StartSession,
+    CancelSession,
+    Reset,
+}
+
+impl ContextState {
+    /// Check whether `operation` is allowed in this state.
+    fn allows(self, operation: Operation) -> bool {
+        use ContextState::*;
+        use Operation::*;
+
+        match (self, operation) {
+            (Connected, CreateSession)
+            | (Created, AuthenticationResponse)
+            | (Authenticated, StartSession) => true,
+            (Started, _) | (Failed, CancelSession) => false,
+            (_, CancelSession) | (_, Reset) => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ContextError {
     Connection(io::Error),
     Protocol(greetd::codec::Error),
-    Internal(String),
+    InvalidTransition(ContextState, Operation),
     SocketMissing,
+    SocketNotFound(PathBuf),
+    PermissionDenied(PathBuf),
+    NotGreetd(PathBuf),
+    Timeout,
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Connection(err) => write!(f, "Connection error: {}", err),
+            Self::Protocol(err) => write!(f, "Protocol error: {}", err),
+            Self::InvalidTransition(state, operation) => {
+                write!(f, "Cannot perform {:?} in state {:?}", operation, state)
+            }
+            Self::SocketMissing => write!(
+                f,
+                "Environment variable {} missing, set the socket with --socket",
+                ENV_SOCKET
+            ),
+            Self::SocketNotFound(path) => write!(f, "{} does not exist", path.display()),
+            Self::PermissionDenied(path) => {
+                write!(f, "Permission denied to connect to {}", path.display())
+            }
+            Self::NotGreetd(path) => write!(f, "{} is not a greetd socket", path.display()),
+            Self::Timeout => write!(f, "greetd did not answer in time"),
+        }
+    }
+}
+
+impl ContextError {
+    /// Whether the connection broke down and reconnecting may help.
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(
+            self,
+            Self::Connection(_) | Self::Protocol(_) | Self::SocketNotFound(_) | Self::Timeout
+        )
+    }
 }
 
 impl From<io::Error> for ContextError {
@@ -46,33 +116,73 @@ impl From<greetd::codec::Error> for ContextError {
     }
 }
 
-impl From<greetd::Response> for ContextError {
-    fn from(response: greetd::Response) -> Self {
-        match response {
-            greetd::Response::Error { description, .. } => Self::Internal(description),
-            _ => unimplemented!("Cannot convert succesful result into Error type"),
-        }
-    }
-}
-
 pub type ContextResult = Result<greetd::Response, ContextError>;
 
 pub struct GreeterContext {
+    socket_path: PathBuf,
     socket: UnixStream,
+    timeout: Option<Duration>,
     state: ContextState,
+    /// Whether the peer answered like greetd before.
+    verified: bool,
+}
+
+/// The greetd socket to use, `socket` if given or the one from `GREETD_SOCK`.
+pub fn socket_path(socket: Option<&Path>) -> Result<PathBuf, ContextError> {
+    match socket {
+        Some(path) => Ok(path.to_owned()),
+        None => env::var_os(ENV_SOCKET)
+            .map(PathBuf::from)
+            .ok_or(ContextError::SocketMissing),
+    }
+}
+
+fn open_socket(path: &Path, timeout: Option<Duration>) -> Result<UnixStream, ContextError> {
+    let metadata = fs::metadata(path).map_err(|err| socket_error(path, err))?;
+    if !metadata.file_type().is_socket() {
+        return Err(ContextError::NotGreetd(path.to_owned()));
+    }
+
+    let socket = UnixStream::connect(path).map_err(|err| socket_error(path, err))?;
+    socket.set_read_timeout(timeout)?;
+    socket.set_write_timeout(timeout)?;
+
+    Ok(socket)
+}
+
+fn socket_error(path: &Path, err: io::Error) -> ContextError {
+    match err.kind() {
+        io::ErrorKind::NotFound => ContextError::SocketNotFound(path.to_owned()),
+        io::ErrorKind::PermissionDenied => ContextError::PermissionDenied(path.to_owned()),
+        _ => ContextError::Connection(err),
+    }
 }
 
 impl GreeterContext {
-    pub fn connect() -> Result<Self, ContextError> {
-        let sock_addr = env::var(ENV_SOCKET).map_err(|_| ContextError::SocketMissing)?;
-        let socket = UnixStream::connect(sock_addr)?;
+    /// Connect to the greetd socket at `path`, later resets reconnect there.
+    ///
+    /// Requests fail with `ContextError::Timeout` if greetd does not answer
+    /// within `timeout`.
+    pub fn connect(
+        path: impl AsRef<Path>,
+        timeout: Option<Duration>,
+    ) -> Result<Self, ContextError> {
+        let socket_path = path.as_ref().to_owned();
+        let socket = open_socket(&socket_path, timeout)?;
 
         Ok(Self {
+            socket_path,
             socket,
+            timeout,
             state: ContextState::Connected,
+            verified: false,
         })
     }
 
+    pub fn is_connected(&self) -> bool {
+        self.state == ContextState::Connected
+    }
+
     pub fn is_started(&self) -> bool {
         self.state == ContextState::Started
     }
@@ -81,47 +191,64 @@ impl GreeterContext {
         self.state == ContextState::Failed
     }
 
+    /// Fail with `ContextError::InvalidTransition` if `operation` is not allowed.
+    fn check(&self, operation: Operation) -> Result<(), ContextError> {
+        if self.state.allows(operation) {
+            Ok(())
+        } else {
+            Err(ContextError::InvalidTransition(self.state, operation))
+        }
+    }
+
     pub fn reset(&mut self) -> Result<(), ContextError> {
-        match self.state {
-            ContextState::Started => panic!("Cannot reset connection for started session"),
-            ContextState::Failed => {}
-            _ => self.cancel()?,
+        self.check(Operation::Reset)?;
+        if self.state != ContextState::Failed {
+            self.cancel()?;
         }
 
-        let sock_addr = env::var(ENV_SOCKET).map_err(|_| ContextError::SocketMissing)?;
-        self.socket = UnixStream::connect(sock_addr)?;
+        self.socket = open_socket(&self.socket_path, self.timeout)?;
         self.state = ContextState::Connected;
 
         Ok(())
     }
 
+    /// Create a session for the username or answer the pending prompt with `data`.
     pub fn send_request(&mut self, data: String, greeter: &mut impl GreetUI) -> ContextResult {
-        let reply = match self.state {
-            ContextState::Connected => self.create_session(data),
-            ContextState::Created => self.authentication_response(Some(data)),
-            ContextState::Failed => panic!("Cannot send data with failed connection"),
-            _ => panic!("Cannot send request to session in state {:?}", self.state),
-        };
+        if self.state == ContextState::Connected {
+            self.create_session(data, greeter)
+        } else {
+            self.respond(data, greeter)
+        }
+    }
+
+    pub fn create_session(
+        &mut self,
+        username: String,
+        greeter: &mut impl GreetUI,
+    ) -> ContextResult {
+        self.check(Operation::CreateSession)?;
+        let reply = self.exchange(greetd::Request::CreateSession { username });
+
+        self.handle_response(reply, greeter)
+    }
+
+    pub fn respond(&mut self, response: String, greeter: &mut impl GreetUI) -> ContextResult {
+        self.check(Operation::AuthenticationResponse)?;
+        let reply = self.authentication_response(Some(response));
 
         self.handle_response(reply, greeter)
     }
 
     pub fn start(&mut self, command: Vec<String>, greeter: &mut impl GreetUI) -> ContextResult {
-        let reply = match self.state {
-            ContextState::Authenticated => self.start_session(command),
-            ContextState::Failed => panic!("Cannot start session with failed connection"),
-            _ => panic!("Cannot start session in state {:?}", self.state),
-        };
+        self.check(Operation::StartSession)?;
+        let reply = self.start_session(command);
 
         self.handle_response(reply, greeter)
     }
 
     pub fn cancel(&mut self) -> Result<(), ContextError> {
-        let reply = match self.state {
-            ContextState::Started => panic!("Cannot cancel already started session"),
-            ContextState::Failed => panic!("Cannot close failed connection"),
-            _ => self.cancel_session(),
-        };
+        self.check(Operation::CancelSession)?;
+        let reply = self.cancel_session();
 
         self.state = match reply {
             Err(_) => ContextState::Failed,
@@ -131,62 +258,36 @@ impl GreeterContext {
         reply
     }
 
-    fn create_session(&mut self, username: String) -> ContextResult {
-        debug_assert!(
-            self.state == ContextState::Connected,
-            format!("Cannot create session: Invalid state {:?}", self.state)
-        );
-
-        let request = greetd::Request::CreateSession { username };
-        request.write_to(&mut self.socket)?;
-        let response = greetd_ipc::Response::read_from(&mut self.socket)?;
-
-        Ok(response)
+    /// Send `request` and wait for the answer of greetd.
+    fn exchange(&mut self, request: greetd::Request) -> ContextResult {
+        let mut stream = WatchedStream {
+            stream: &mut self.socket,
+            timed_out: false,
+        };
+        let result = request
+            .write_to(&mut stream)
+            .and_then(|()| greetd::Response::read_from(&mut stream));
+
+        // The codec only keeps the message of I/O errors
+        result.map_err(|err| {
+            if stream.timed_out {
+                ContextError::Timeout
+            } else {
+                ContextError::from(err)
+            }
+        })
     }
 
     fn authentication_response(&mut self, response: Option<String>) -> ContextResult {
-        debug_assert!(
-            self.state == ContextState::Created,
-            format!(
-                "Cannot reply to authentication message: Invalid state {:?}",
-                self.state
-            )
-        );
-
-        let request = greetd::Request::PostAuthMessageResponse { response };
-        request.write_to(&mut self.socket)?;
-        let response = greetd_ipc::Response::read_from(&mut self.socket)?;
-
-        Ok(response)
+        self.exchange(greetd::Request::PostAuthMessageResponse { response })
     }
 
     fn start_session(&mut self, command: Vec<String>) -> ContextResult {
-        debug_assert!(
-            self.state == ContextState::Authenticated,
-            format!("Cannot start session: Invalid state {:?}", self.state)
-        );
-
-        let request = greetd::Request::StartSession { cmd: command };
-        request.write_to(&mut self.socket)?;
-        let response = greetd_ipc::Response::read_from(&mut self.socket)?;
-
-        Ok(response)
+        self.exchange(greetd::Request::StartSession { cmd: command })
     }
 
     fn cancel_session(&mut self) -> Result<(), ContextError> {
-        debug_assert!(
-            self.state != ContextState::Failed,
-            format!(
-                "Cannot cancel current session: Invalid state {:?}",
-                self.state
-            )
-        );
-
-        let request = greetd::Request::CancelSession;
-        request.write_to(&mut self.socket)?;
-        greetd_ipc::Response::read_from(&mut self.socket)?;
-
-        Ok(())
+        self.exchange(greetd::Request::CancelSession).map(drop)
     }
 
     fn handle_response(
@@ -194,12 +295,27 @@ impl GreeterContext {
         reply: ContextResult,
         greeter: &mut impl GreetUI,
     ) -> ContextResult {
+        // Garbage in the very first answer means we are not talking to greetd
+        let reply = match reply {
+            Err(ContextError::Protocol(greetd::codec::Error::Serialization(_)))
+                if !self.verified =>
+            {
+                Err(ContextError::NotGreetd(self.socket_path.clone()))
+            }
+            Ok(response) => {
+                self.verified = true;
+                Ok(response)
+            }
+            Err(err) => Err(err),
+        };
+
         self.state = match reply {
             Err(_) => ContextState::Failed,
             Ok(ref response) => match *response {
+                // Cancelling updates the state, a failed connection is reset later
                 greetd::Response::Error { .. } => {
-                    self.cancel()?;
-                    ContextState::Connected
+                    let _ = self.cancel();
+                    self.state
                 }
                 greetd::Response::AuthMessage { .. } => ContextState::Created,
                 greetd::Response::Success => match self.state {
@@ -244,20 +360,235 @@ impl GreeterContext {
                         greeter.show_authentication_failure(description);
                         Ok(response)
                     }
-                    greetd::ErrorType::Error => Err(ContextError::from(response)),
+                    greetd::ErrorType::Error => {
+                        greeter.show_session_failure(description);
+                        Ok(response)
+                    }
                 },
             },
         }
     }
 }
 
-impl Drop for GreeterContext {
-    fn drop(&mut self) {
-        match self.state {
-            ContextState::Started | ContextState::Failed => {}
-            _ => {
-                let _ = self.cancel();
+/// Stream remembering whether a read or write timed out.
+struct WatchedStream<'a> {
+    stream: &'a mut UnixStream,
+    timed_out: bool,
+}
+
+impl WatchedStream<'_> {
+    fn watch<T>(&mut self, result: io::Result<T>) -> io::Result<T> {
+        if let Err(ref err) = result {
+            if let io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut = err.kind() {
+                self.timed_out = true;
+            }
+        }
+        result
+    }
+}
+
+impl Read for WatchedStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.stream.read(buf);
+        self.watch(result)
+    }
+}
+
+impl Write for WatchedStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.stream.write(buf);
+        self.watch(result)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.stream.flush();
+        self.watch(result)
+    }
+}
+
+impl AsRawFd for GreeterContext {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::net::UnixListener;
+    use std::sync::Once;
+    use std::thread;
+
+    struct NullUI;
+
+    impl GreetUI for NullUI {
+        fn set_prompt(&mut self, _prompt: &str) {}
+        fn set_secret_prompt(&mut self, _prompt: &str) {}
+        fn show_info_message(&mut self, _message: &str) {}
+        fn show_error_message(&mut self, _message: &str) {}
+        fn show_authentication_failure(&mut self, _reason: &str) {}
+        fn show_session_failure(&mut self, _reason: &str) {}
+    }
+
+    /// A listener that accepts the reconnect on reset.
+    ///
+    /// Connections are closed right away, so later requests fail instead of blocking.
+    fn listen() -> PathBuf {
+        static LISTEN: Once = Once::new();
+        let path = env::temp_dir().join(format!("greetly-test-{}.sock", std::process::id()));
+        LISTEN.call_once(|| {
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+            thread::spawn(move || for _ in listener.incoming() {});
+        });
+        path
+    }
+
+    /// A context in `state` and the greetd end of its connection.
+    fn context(state: ContextState) -> (GreeterContext, UnixStream) {
+        let (socket, peer) = UnixStream::pair().unwrap();
+        let socket_path = listen();
+        (
+            GreeterContext {
+                socket_path,
+                socket,
+                timeout: None,
+                state,
+                verified: true,
+            },
+            peer,
+        )
+    }
+
+    fn perform(ctx: &mut GreeterContext, operation: Operation) -> Result<(), ContextError> {
+        match operation {
+            Operation::CreateSession => ctx
+                .create_session("alice".to_owned(), &mut NullUI)
+                .map(drop),
+            Operation::AuthenticationResponse => {
+                ctx.respond("secret".to_owned(), &mut NullUI).map(drop)
             }
+            Operation::StartSession => ctx.start(vec!["true".to_owned()], &mut NullUI).map(drop),
+            Operation::CancelSession => ctx.cancel(),
+            Operation::Reset => ctx.reset(),
         }
     }
+
+    macro_rules! transition {
+        ($name:ident: $state:ident, $operation:ident => invalid) => {
+            #[test]
+            fn $name() {
+                let (mut ctx, _peer) = context(ContextState::$state);
+                match perform(&mut ctx, Operation::$operation) {
+                    Err(ContextError::InvalidTransition(state, operation)) => {
+                        assert_eq!(state, ContextState::$state);
+                        assert_eq!(operation, Operation::$operation);
+                    }
+                    result => panic!("Unexpected result {:?}", result),
+                }
+                assert_eq!(ctx.state, ContextState::$state);
+            }
+        };
+        ($name:ident: $state:ident, $operation:ident => $next:ident) => {
+            #[test]
+            fn $name() {
+                let (mut ctx, mut peer) = context(ContextState::$state);
+                greetd::Response::Success.write_to(&mut peer).unwrap();
+                perform(&mut ctx, Operation::$operation).unwrap();
+                assert_eq!(ctx.state, ContextState::$next);
+                drop(peer);
+            }
+        };
+    }
+
+    transition!(connected_create: Connected, CreateSession => Authenticated);
+    transition!(connected_respond: Connected, AuthenticationResponse => invalid);
+    transition!(connected_start: Connected, StartSession => invalid);
+    transition!(connected_cancel: Connected, CancelSession => Connected);
+    transition!(connected_reset: Connected, Reset => Connected);
+
+    transition!(created_create: Created, CreateSession => invalid);
+    transition!(created_respond: Created, AuthenticationResponse => Authenticated);
+    transition!(created_start: Created, StartSession => invalid);
+    transition!(created_cancel: Created, CancelSession => Connected);
+    transition!(created_reset: Created, Reset => Connected);
+
+    transition!(authenticated_create: Authenticated, CreateSession => invalid);
+    transition!(authenticated_respond: Authenticated, AuthenticationResponse => invalid);
+    transition!(authenticated_start: Authenticated, StartSession => Started);
+    transition!(authenticated_cancel: Authenticated, CancelSession => Connected);
+    transition!(authenticated_reset: Authenticated, Reset => Connected);
+
+    transition!(started_create: Started, CreateSession => invalid);
+    transition!(started_respond: Started, AuthenticationResponse => invalid);
+    transition!(started_start: Started, StartSession => invalid);
+    transition!(started_cancel: Started, CancelSession => invalid);
+    transition!(started_reset: Started, Reset => invalid);
+
+    transition!(failed_create: Failed, CreateSession => invalid);
+    transition!(failed_respond: Failed, AuthenticationResponse => invalid);
+    transition!(failed_start: Failed, StartSession => invalid);
+    transition!(failed_cancel: Failed, CancelSession => invalid);
+    transition!(failed_reset: Failed, Reset => Connected);
+
+    #[test]
+    fn error_response_returns_to_connected() {
+        let (mut ctx, mut peer) = context(ContextState::Authenticated);
+        greetd::Response::Error {
+            error_type: greetd::ErrorType::Error,
+            description: "exec failed".to_owned(),
+        }
+        .write_to(&mut peer)
+        .unwrap();
+        greetd::Response::Success.write_to(&mut peer).unwrap();
+
+        let response = ctx.start(vec!["true".to_owned()], &mut NullUI).unwrap();
+        assert!(matches!(response, greetd::Response::Error { .. }));
+        assert_eq!(ctx.state, ContextState::Connected);
+        drop(peer);
+    }
+
+    #[test]
+    fn missing_socket_is_reported() {
+        let path = env::temp_dir().join("greetly-test-missing.sock");
+        match GreeterContext::connect(&path, None) {
+            Err(ContextError::SocketNotFound(p)) => assert_eq!(p, path),
+            Err(err) => panic!("Unexpected error {}", err),
+            Ok(_) => panic!("Connected to missing socket"),
+        }
+    }
+
+    #[test]
+    fn regular_file_is_not_greetd() {
+        let path = env::temp_dir().join(format!("greetly-test-{}.file", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let result = GreeterContext::connect(&path, None);
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(result, Err(ContextError::NotGreetd(_))));
+    }
+
+    #[test]
+    fn garbage_answer_is_not_greetd() {
+        let (mut ctx, mut peer) = context(ContextState::Connected);
+        ctx.verified = false;
+        io::Write::write_all(&mut peer, b"\x05\x00\x00\x00hello").unwrap();
+
+        let result = ctx.send_request("alice".to_owned(), &mut NullUI);
+        assert!(matches!(result, Err(ContextError::NotGreetd(_))));
+        assert!(ctx.is_failed());
+        drop(peer);
+    }
+
+    #[test]
+    fn silent_greetd_times_out() {
+        let (mut ctx, peer) = context(ContextState::Connected);
+        let timeout = Some(Duration::from_millis(20));
+        ctx.socket.set_read_timeout(timeout).unwrap();
+
+        let result = ctx.send_request("alice".to_owned(), &mut NullUI);
+        assert!(matches!(result, Err(ContextError::Timeout)));
+        assert!(ctx.is_failed());
+        drop(peer);
+    }
 }
@@ -0,0 +1,353 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use greetd_ipc as greetd;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use greetd::codec::SyncCodec;
+
+use crate::config::ConfigError;
+use crate::users::User;
+
+/// A single message of the scripted PAM conversation.
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Visible prompt and the expected answer.
+    Visible(String, String),
+    /// Secret prompt and the expected answer.
+    Secret(String, String),
+    Info(String),
+    Error(String),
+}
+
+/// A fake account with its PAM conversation.
+#[derive(Debug, Clone)]
+pub struct Account {
+    name: String,
+    real_name: Option<String>,
+    steps: Vec<Step>,
+    start_error: Option<String>,
+    hang_up: bool,
+}
+
+impl Account {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            real_name: None,
+            steps: Vec::new(),
+            start_error: None,
+            hang_up: false,
+        }
+    }
+
+    /// Ask for `password` with a `Password:` prompt.
+    pub fn password(self, password: &str) -> Self {
+        self.step(Step::Secret("Password:".to_owned(), password.to_owned()))
+    }
+
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Refuse to start any session with `error`.
+    pub fn start_error(mut self, error: &str) -> Self {
+        self.start_error = Some(error.to_owned());
+        self
+    }
+
+    /// Drop the connection instead of answering the session request.
+    pub fn hang_up(mut self) -> Self {
+        self.hang_up = true;
+        self
+    }
+
+    /// The account as it would appear in the passwd file.
+    pub fn user(&self, uid: u32) -> User {
+        User {
+            name: self.name.clone(),
+            uid,
+            real_name: self.real_name.clone(),
+            shell: "/bin/sh".to_owned(),
+        }
+    }
+}
+
+/// An account as written in the demo accounts file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccountConfig {
+    name: String,
+    real_name: Option<String>,
+    /// Message shown before asking for the password.
+    info: Option<String>,
+    password: Option<String>,
+    /// Error message shown after the password was accepted.
+    warning: Option<String>,
+    /// Second factor asked for after the password.
+    token: Option<String>,
+    start_error: Option<String>,
+    /// Drop the connection on login to try reconnecting.
+    #[serde(default)]
+    hang_up: bool,
+}
+
+impl From<AccountConfig> for Account {
+    fn from(config: AccountConfig) -> Self {
+        let mut account = Self::new(&config.name);
+        account.real_name = config.real_name;
+        account.start_error = config.start_error;
+        if let Some(info) = config.info {
+            account = account.step(Step::Info(info));
+        }
+        if let Some(password) = config.password {
+            account = account.password(&password);
+        }
+        if let Some(warning) = config.warning {
+            account = account.step(Step::Error(warning));
+        }
+        if let Some(token) = config.token {
+            account = account.step(Step::Visible("Token:".to_owned(), token));
+        }
+        if config.hang_up {
+            account = account.hang_up();
+        }
+        account
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccountsFile {
+    #[serde(default)]
+    account: Vec<AccountConfig>,
+}
+
+/// Load the `[[account]]` entries of the TOML file at `path`.
+pub fn load_accounts(path: &Path) -> Result<Vec<Account>, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_owned(), err))?;
+    let file: AccountsFile =
+        toml::from_str(&content).map_err(|err| ConfigError::from_toml(path, err))?;
+
+    Ok(file.account.into_iter().map(Account::from).collect())
+}
+
+/// Accounts used when no accounts file is given.
+pub fn demo_accounts() -> Vec<Account> {
+    vec![
+        Account::new("demo").password("demo"),
+        Account::new("alice")
+            .password("alice")
+            .step(Step::Error("A second factor is required".to_owned()))
+            .step(Step::Visible("Token:".to_owned(), "123456".to_owned())),
+        Account::new("bob")
+            .step(Step::Info("Your password expires in 3 days".to_owned()))
+            .password("bob"),
+        Account::new("broken")
+            .password("broken")
+            .start_error("Failed to execute session"),
+    ]
+}
+
+/// A session greetd was asked to start, by user name and command.
+pub type StartedSession = (String, Vec<String>);
+
+/// Stand-in for greetd listening on a temporary socket.
+///
+/// Every connection is served on its own thread following the accounts'
+/// scripted conversations. Started sessions are recorded instead of run.
+pub struct FakeGreetd {
+    path: PathBuf,
+    started: Arc<Mutex<Vec<StartedSession>>>,
+}
+
+impl FakeGreetd {
+    pub fn spawn(accounts: Vec<Account>) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = env::temp_dir().join(format!(
+            "greetly-fake-greetd-{}-{}.sock",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+
+        let started = Arc::new(Mutex::new(Vec::new()));
+        let accounts = Arc::new(accounts);
+        {
+            let started = started.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming().filter_map(Result::ok) {
+                    let mut conn = Connection {
+                        stream,
+                        accounts: accounts.clone(),
+                        started: started.clone(),
+                        session: None,
+                    };
+                    thread::spawn(move || conn.serve());
+                }
+            });
+        }
+
+        Ok(Self { path, started })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn started(&self) -> Vec<StartedSession> {
+        self.started.lock().unwrap().clone()
+    }
+}
+
+impl Drop for FakeGreetd {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Progress of the session currently being configured.
+struct Pending {
+    account: usize,
+    step: usize,
+}
+
+struct Connection {
+    stream: UnixStream,
+    accounts: Arc<Vec<Account>>,
+    started: Arc<Mutex<Vec<StartedSession>>>,
+    session: Option<Pending>,
+}
+
+impl Connection {
+    fn serve(&mut self) {
+        while let Ok(request) = greetd::Request::read_from(&mut self.stream) {
+            let response = match self.handle(request) {
+                Some(response) => response,
+                None => break,
+            };
+            if response.write_to(&mut self.stream).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// The answer to `request`, `None` to hang up.
+    fn handle(&mut self, request: greetd::Request) -> Option<greetd::Response> {
+        if let greetd::Request::CreateSession { ref username } = request {
+            if self
+                .accounts
+                .iter()
+                .any(|a| &a.name == username && a.hang_up)
+            {
+                return None;
+            }
+        }
+
+        Some(self.answer(request))
+    }
+
+    fn answer(&mut self, request: greetd::Request) -> greetd::Response {
+        match request {
+            greetd::Request::CreateSession { username } => {
+                if self.session.is_some() {
+                    return error("a session is already being configured");
+                }
+                match self.accounts.iter().position(|a| a.name == username) {
+                    Some(account) => {
+                        self.session = Some(Pending { account, step: 0 });
+                        self.next_message()
+                    }
+                    None => auth_error("unknown user"),
+                }
+            }
+            greetd::Request::PostAuthMessageResponse { response } => {
+                let pending = match self.session {
+                    Some(ref mut pending) => pending,
+                    None => return error("no session is being configured"),
+                };
+                let expected = match self.accounts[pending.account].steps.get(pending.step) {
+                    Some(Step::Visible(_, answer)) | Some(Step::Secret(_, answer)) => Some(answer),
+                    Some(_) => None,
+                    None => return error("session is already authenticated"),
+                };
+                if expected.is_some() && response.as_ref() != expected {
+                    self.session = None;
+                    return auth_error("authentication failed");
+                }
+                pending.step += 1;
+                self.next_message()
+            }
+            greetd::Request::StartSession { cmd } => {
+                let account = match self.session {
+                    Some(ref pending)
+                        if pending.step >= self.accounts[pending.account].steps.len() =>
+                    {
+                        &self.accounts[pending.account]
+                    }
+                    _ => return error("session is not authenticated"),
+                };
+                if let Some(ref err) = account.start_error {
+                    return error(err);
+                }
+                let name = account.name.clone();
+                self.started.lock().unwrap().push((name, cmd));
+                self.session = None;
+                greetd::Response::Success
+            }
+            greetd::Request::CancelSession => {
+                self.session = None;
+                greetd::Response::Success
+            }
+        }
+    }
+
+    /// The next message of the conversation, `Success` once it is complete.
+    fn next_message(&self) -> greetd::Response {
+        let pending = self.session.as_ref().expect("no pending session");
+        let (auth_message_type, auth_message) =
+            match self.accounts[pending.account].steps.get(pending.step) {
+                Some(Step::Visible(prompt, _)) => (greetd::AuthMessageType::Visible, prompt),
+                Some(Step::Secret(prompt, _)) => (greetd::AuthMessageType::Secret, prompt),
+                Some(Step::Info(message)) => (greetd::AuthMessageType::Info, message),
+                Some(Step::Error(message)) => (greetd::AuthMessageType::Error, message),
+                None => return greetd::Response::Success,
+            };
+
+        greetd::Response::AuthMessage {
+            auth_message_type,
+            auth_message: auth_message.clone(),
+        }
+    }
+}
+
+fn error(description: &str) -> greetd::Response {
+    greetd::Response::Error {
+        error_type: greetd::ErrorType::Error,
+        description: description.to_owned(),
+    }
+}
+
+fn auth_error(description: &str) -> greetd::Response {
+    greetd::Response::Error {
+        error_type: greetd::ErrorType::AuthError,
+        description: description.to_owned(),
+    }
+}
@@ -11,15 +11,31 @@ use greetd_ipc as greetd;
 use std::env;
 use std::fmt;
 use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::Duration;
 
+mod config;
 mod context;
+mod fake_greetd;
+mod power;
+mod session;
+mod state;
 mod tui;
+mod users;
 
+use config::{Config, ConfigError};
 use context::{ContextError, GreeterContext};
+use fake_greetd::{Account, FakeGreetd};
+use power::PowerAction;
+use session::{SessionError, SessionLauncher};
+use state::State;
 use tui::TerminalUI;
-use tui::{LoginAction, LoginError};
+use tui::{GreetUI, LoginAction, LoginError, LoginUI};
+use users::User;
 
-const SESSION_COMMAND: &str = "/bin/bash";
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
 
 #[derive(Debug)]
 enum GreetLyError {
@@ -28,6 +44,7 @@ enum GreetLyError {
     Connection(io::Error),
     Protocol(greetd::codec::Error),
     Session(String),
+    Socket(String),
     UI(String),
     Unauthenticated,
 }
@@ -50,6 +67,18 @@ impl From<greetd::codec::Error> for GreetLyError {
     }
 }
 
+impl From<ConfigError> for GreetLyError {
+    fn from(err: ConfigError) -> Self {
+        Self::Configuration(err.to_string())
+    }
+}
+
+impl From<SessionError> for GreetLyError {
+    fn from(err: SessionError) -> Self {
+        Self::Configuration(err.to_string())
+    }
+}
+
 impl From<LoginError> for GreetLyError {
     fn from(err: LoginError) -> Self {
         Self::UI(err.to_string())
@@ -61,10 +90,12 @@ impl From<ContextError> for GreetLyError {
         match err {
             ContextError::Connection(err) => err.into(),
             ContextError::Protocol(err) => err.into(),
-            ContextError::Internal(msg) => Self::Session(msg),
-            ContextError::SocketMissing => {
-                Self::Configuration("Environment variable GREETD_SOCK missing".to_owned())
-            }
+            ContextError::InvalidTransition(..) => Self::Session(err.to_string()),
+            ContextError::SocketMissing
+            | ContextError::SocketNotFound(_)
+            | ContextError::PermissionDenied(_)
+            | ContextError::NotGreetd(_)
+            | ContextError::Timeout => Self::Socket(err.to_string()),
         }
     }
 }
@@ -77,40 +108,278 @@ impl fmt::Display for GreetLyError {
             Self::Connection(err) => write!(f, "Conection to greetd failed: {}", err),
             Self::Protocol(err) => write!(f, "Communication with greetd failed: {}", err),
             Self::Session(msg) => write!(f, "Starting the user session failed: {}", msg),
+            Self::Socket(msg) => write!(f, "Cannot connect to greetd: {}", msg),
             Self::UI(msg) => write!(f, "UI Error: {}", msg),
             Self::Unauthenticated => write!(f, "Session not authenticated"),
         }
     }
 }
 
-fn run_greetly() -> Result<(), GreetLyError> {
-    let mut session_ctx = GreeterContext::connect()?;
-    let mut ui = TerminalUI::init()?;
+fn run_power_action(config: &Config, action: PowerAction, ui: &mut impl GreetUI) {
+    if let Err(err) = power::run(&config.power, action) {
+        ui.show_error_message(&err.to_string());
+    }
+}
+
+fn run_greetly(config: &Config) -> Result<(), GreetLyError> {
+    let users = users::load(&config.user);
+    let socket = context::socket_path(config.socket.as_deref())?;
+    let mut session_ctx = GreeterContext::connect(socket, config.timeout())?;
+
+    let result = {
+        let mut ui = TerminalUI::init(config)?;
+        greet(config, &users, &mut session_ctx, &mut ui)
+    };
+    abandon(&mut session_ctx);
+
+    result
+}
+
+/// Run the greeter against a fake greetd serving `accounts`.
+///
+/// Sessions are not started but printed once the greeter exits.
+fn run_demo(config: &Config, accounts: Vec<Account>) -> Result<(), GreetLyError> {
+    let users: Vec<User> = accounts
+        .iter()
+        .zip(1000..)
+        .map(|(account, uid)| account.user(uid))
+        .collect();
+    let greetd = FakeGreetd::spawn(accounts)?;
+    let mut session_ctx = GreeterContext::connect(greetd.path(), config.timeout())?;
+
+    let result = {
+        let mut ui = TerminalUI::init(config)?;
+        greet(config, &users, &mut session_ctx, &mut ui)
+    };
+    abandon(&mut session_ctx);
+
+    for (user, command) in greetd.started() {
+        println!("Would start `{}` for {}", shell_words::join(&command), user);
+    }
+
+    result
+}
+
+/// Adjust `config` so a demo run leaves the system alone.
+fn demo_config(config: &mut Config) {
+    config.state_file = env::temp_dir().join("greetly-demo-state");
+
+    // Keep the enabled power actions visible, but make them do nothing
+    let power = &mut config.power;
+    for command in [
+        &mut power.poweroff,
+        &mut power.reboot,
+        &mut power.suspend,
+        &mut power.hibernate,
+    ] {
+        if command.as_deref().is_some_and(|cmd| !cmd.trim().is_empty()) {
+            *command = Some("true".to_owned());
+        }
+    }
+}
+
+/// Pass on results of a lost connection as `None`, the login loop reconnects.
+fn recover<T>(result: Result<T, ContextError>) -> Result<Option<T>, GreetLyError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.is_connection_lost() => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Cancel the session in progress, a failed connection has none left.
+fn cancel(session_ctx: &mut GreeterContext) -> Result<(), GreetLyError> {
+    if !session_ctx.is_failed() {
+        recover(session_ctx.cancel())?;
+    }
+    Ok(())
+}
+
+/// Cancel a session an error left half authenticated, greetd would keep it
+/// around otherwise.
+fn abandon(session_ctx: &mut GreeterContext) {
+    if !session_ctx.is_connected() && !session_ctx.is_started() {
+        let _ = cancel(session_ctx);
+    }
+}
+
+/// Try to reconnect to greetd once.
+///
+/// On failure `ui` is asked to try again after `delay`, which doubles for the
+/// next attempt.
+fn reconnect(
+    session_ctx: &mut GreeterContext,
+    ui: &mut impl LoginUI,
+    delay: &mut Duration,
+) -> Result<(), GreetLyError> {
+    ui.set_status(Some("Reconnecting to greetd\u{2026}"));
+    match session_ctx.reset() {
+        Ok(()) => {
+            *delay = RECONNECT_DELAY;
+            ui.set_status(None);
+            ui.show_session_failure("Connection to greetd was lost");
+        }
+        Err(err) if err.is_connection_lost() => {
+            ui.schedule_reconnect(*delay);
+            *delay = (*delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    Ok(())
+}
+
+/// Run the login loop until a session is started or the user quits.
+fn greet(
+    config: &Config,
+    users: &[User],
+    session_ctx: &mut GreeterContext,
+    ui: &mut impl LoginUI,
+) -> Result<(), GreetLyError> {
+    let sessions = session::discover(&config.session)?;
+    let launcher = SessionLauncher::new(&config.session)?;
+
+    let default_session = config
+        .session
+        .default
+        .as_ref()
+        .and_then(|id| sessions.iter().position(|session| &session.id == id))
+        .unwrap_or(0);
+
+    ui.set_sessions(&sessions, default_session);
+    ui.set_users(users);
 
-    // TODO: Allow for default user
+    let mut state = State::load(&config.state_file);
+    let mut preset_user = match state.last_user {
+        Some(ref user) if config.user.remember => Some(user.clone()),
+        _ => config.user.default.clone(),
+    };
+    let mut username = None;
+    let mut started_session = None;
+    let mut reconnect_delay = RECONNECT_DELAY;
+    let mut reconnect_scheduled = false;
 
     loop {
+        // Keep handling input between attempts, the UI tells when to retry
+        if session_ctx.is_failed() && !reconnect_scheduled {
+            reconnect(session_ctx, ui, &mut reconnect_delay)?;
+            reconnect_scheduled = session_ctx.is_failed();
+        }
+        // The dead socket of a failed connection is not watched
+        let socket = if session_ctx.is_failed() {
+            -1
+        } else {
+            session_ctx.as_raw_fd()
+        };
+
+        // Skip the username prompt for a preset user
+        let preset = match preset_user {
+            Some(ref user) if session_ctx.is_connected() => Some(user.clone()),
+            _ => None,
+        };
+        let is_preset = preset.is_some();
+
+        let (data, mut session) = match preset {
+            Some(user) => (user, ui.selected_session()),
+            None => match ui.handle_input(socket)? {
+                LoginAction::Submit(data, session) => (data, session),
+                LoginAction::Cancel => {
+                    preset_user = None;
+                    cancel(session_ctx)?;
+                    continue;
+                }
+                LoginAction::PowerOff => {
+                    run_power_action(config, PowerAction::PowerOff, ui);
+                    continue;
+                }
+                LoginAction::Reboot => {
+                    run_power_action(config, PowerAction::Reboot, ui);
+                    continue;
+                }
+                LoginAction::Suspend => {
+                    run_power_action(config, PowerAction::Suspend, ui);
+                    continue;
+                }
+                LoginAction::Hibernate => {
+                    run_power_action(config, PowerAction::Hibernate, ui);
+                    continue;
+                }
+                LoginAction::Disconnected => {
+                    // Fails on the dead socket, the connection is restored above
+                    cancel(session_ctx)?;
+                    continue;
+                }
+                LoginAction::Reconnect => {
+                    reconnect_scheduled = false;
+                    continue;
+                }
+                LoginAction::Quit => {
+                    cancel(session_ctx)?;
+                    break;
+                }
+            },
+        };
+
         if session_ctx.is_failed() {
-            session_ctx.reset()?;
+            ui.show_error_message("Not connected to greetd, please wait");
+            continue;
         }
 
-        match ui.handle_input().unwrap() {
-            LoginAction::Cancel => {
-                session_ctx.cancel()?;
+        if session_ctx.is_connected() {
+            let display_name = users
+                .iter()
+                .find(|user| user.name == data)
+                .map_or(data.as_str(), |user| user.display_name());
+            ui.set_user(Some(display_name));
+            if config.session.remember {
+                if let Some(index) = state
+                    .sessions
+                    .get(&data)
+                    .and_then(|id| sessions.iter().position(|session| &session.id == id))
+                {
+                    ui.select_session(index);
+                    // greetd may start the session without asking anything
+                    session = ui.selected_session();
+                }
             }
-            LoginAction::Submit(data) => {
-                if let greetd::Response::Success = session_ctx.send_request(data, &mut ui)? {
-                    if let greetd::Response::Success =
-                        session_ctx.start(vec![SESSION_COMMAND.to_owned()], &mut ui)?
-                    {
+            username = Some(data.clone());
+        }
+
+        let response = match recover(session_ctx.send_request(data, ui))? {
+            Some(response) => response,
+            None => continue,
+        };
+        match response {
+            greetd::Response::Success => {
+                let command = launcher.command(&sessions[session]);
+                match recover(session_ctx.start(command, ui))? {
+                    Some(greetd::Response::Success) => {
+                        started_session = Some(session);
                         break;
                     }
-                };
-            }
-            LoginAction::Quit => {
-                session_ctx.cancel()?;
-                break;
+                    // Ask for the user again, a preset one would fail the same way
+                    Some(_) => preset_user = None,
+                    None => {}
+                }
             }
+            // Do not retry a preset user greetd refuses right away
+            greetd::Response::Error { .. } if is_preset => preset_user = None,
+            _ => {}
+        }
+    }
+
+    if let (Some(user), Some(session)) = (username, started_session) {
+        if config.session.remember {
+            state
+                .sessions
+                .insert(user.clone(), sessions[session].id.clone());
+        }
+        if config.user.remember {
+            state.last_user = Some(user);
+        }
+        if config.session.remember || config.user.remember {
+            // The session is already running, a stale state file is no reason to fail
+            let _ = state.save(&config.state_file);
         }
     }
 
@@ -134,11 +403,23 @@ fn main() {
         "CONFIG_FILE",
     );
     opts.optopt("e", "cmd", "command to run", "COMMAND");
+    opts.optopt(
+        "s",
+        "socket",
+        "greetd socket to connect to (default: $GREETD_SOCK)",
+        "SOCKET",
+    );
+    opts.optflagopt(
+        "",
+        "demo",
+        "run against a fake greetd with the accounts from ACCOUNTS_FILE",
+        "ACCOUNTS_FILE",
+    );
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(f) => {
-            eprintln!("{}", f.to_string());
+            eprintln!("{}", f);
             eprintln!("Usage: {} [options]", program);
             eprintln!("See {} --help for a list of supported options", program);
             std::process::exit(1);
@@ -151,8 +432,360 @@ fn main() {
         std::process::exit(0);
     }
 
-    if let Err(err) = run_greetly() {
+    let mut config = match Config::load(matches.opt_str("c").as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Error: {}", GreetLyError::from(err));
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(socket) = matches.opt_str("s") {
+        config.socket = Some(socket.into());
+    }
+
+    if let Some(command) = matches.opt_str("e") {
+        config.session.command = command;
+        config.session.default = Some(session::COMMAND_SESSION_ID.to_owned());
+    }
+
+    let result = if matches.opt_present("demo") {
+        let accounts = match matches.opt_str("demo") {
+            Some(path) => fake_greetd::load_accounts(Path::new(&path)),
+            None => Ok(fake_greetd::demo_accounts()),
+        };
+        let accounts = match accounts {
+            Ok(accounts) => accounts,
+            Err(err) => {
+                eprintln!("Error: {}", GreetLyError::from(err));
+                std::process::exit(1);
+            }
+        };
+
+        demo_config(&mut config);
+        run_demo(&config, accounts)
+    } else {
+        run_greetly(&config)
+    };
+
+    if let Err(err) = result {
         eprintln!("Error: {}", err);
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::VecDeque;
+    use std::os::unix::io::RawFd;
+    use std::path::PathBuf;
+
+    use fake_greetd::Step;
+    use session::Session;
+
+    /// UI replaying a fixed list of actions and recording what it is shown.
+    #[derive(Default)]
+    struct HeadlessUI {
+        input: VecDeque<LoginAction>,
+        prompts: Vec<String>,
+        messages: Vec<String>,
+        statuses: Vec<Option<String>>,
+        reconnects: Vec<Duration>,
+        session: usize,
+    }
+
+    impl HeadlessUI {
+        fn new(input: &[&str]) -> Self {
+            Self {
+                input: input
+                    .iter()
+                    .map(|text| LoginAction::Submit(text.to_string(), 0))
+                    .collect(),
+                ..Self::default()
+            }
+        }
+    }
+
+    impl GreetUI for HeadlessUI {
+        fn set_prompt(&mut self, prompt: &str) {
+            self.prompts.push(prompt.to_owned());
+        }
+
+        fn set_secret_prompt(&mut self, prompt: &str) {
+            self.prompts.push(prompt.to_owned());
+        }
+
+        fn show_info_message(&mut self, message: &str) {
+            self.messages.push(format!("Info: {}", message));
+        }
+
+        fn show_error_message(&mut self, message: &str) {
+            self.messages.push(format!("Error: {}", message));
+        }
+
+        fn show_authentication_failure(&mut self, reason: &str) {
+            self.messages
+                .push(format!("Authentication failed: {}", reason));
+        }
+
+        fn show_session_failure(&mut self, reason: &str) {
+            self.messages.push(format!("Login failed: {}", reason));
+        }
+    }
+
+    impl LoginUI for HeadlessUI {
+        fn handle_input(&mut self, _socket: RawFd) -> Result<LoginAction, LoginError> {
+            Ok(self.input.pop_front().unwrap_or(LoginAction::Quit))
+        }
+
+        fn set_sessions(&mut self, _sessions: &[Session], selected: usize) {
+            self.session = selected;
+        }
+
+        fn set_users(&mut self, _users: &[User]) {}
+
+        fn set_user(&mut self, _user: Option<&str>) {}
+
+        fn select_session(&mut self, index: usize) {
+            self.session = index;
+        }
+
+        fn selected_session(&self) -> usize {
+            self.session
+        }
+
+        fn set_status(&mut self, status: Option<&str>) {
+            self.statuses.push(status.map(str::to_owned));
+        }
+
+        fn schedule_reconnect(&mut self, after: Duration) {
+            self.reconnects.push(after);
+        }
+    }
+
+    fn test_config() -> Config {
+        let mut config = Config {
+            state_file: PathBuf::from("/nonexistent/state"),
+            ..Config::default()
+        };
+        config.user.remember = false;
+        config.user.passwd_file = PathBuf::from("/nonexistent/passwd");
+        config.session.command = "true".to_owned();
+        config.session.remember = false;
+        config.session.wayland_sessions.clear();
+        config.session.x11_sessions.clear();
+        config
+    }
+
+    fn run(
+        config: &Config,
+        accounts: Vec<Account>,
+        ui: &mut HeadlessUI,
+    ) -> (Result<(), GreetLyError>, FakeGreetd) {
+        let greetd = FakeGreetd::spawn(accounts).unwrap();
+        let mut session_ctx = GreeterContext::connect(greetd.path(), None).unwrap();
+        let result = greet(config, &[], &mut session_ctx, ui);
+        (result, greetd)
+    }
+
+    fn started_users(greetd: &FakeGreetd) -> Vec<String> {
+        greetd.started().into_iter().map(|(user, _)| user).collect()
+    }
+
+    #[test]
+    fn password_login_starts_session() {
+        let mut ui = HeadlessUI::new(&["alice", "hunter2"]);
+        let accounts = vec![Account::new("alice").password("hunter2")];
+        let (result, greetd) = run(&test_config(), accounts, &mut ui);
+
+        assert!(result.is_ok());
+        assert_eq!(ui.prompts, ["Password:"]);
+        let started = greetd.started();
+        assert_eq!(started.len(), 1);
+        assert_eq!(started[0].0, "alice");
+        assert_eq!(started[0].1.last().map(String::as_str), Some("true"));
+    }
+
+    #[test]
+    fn wrong_password_asks_again() {
+        let mut ui = HeadlessUI::new(&["alice", "wrong", "alice", "hunter2"]);
+        let accounts = vec![Account::new("alice").password("hunter2")];
+        let (result, greetd) = run(&test_config(), accounts, &mut ui);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            ui.messages,
+            ["Authentication failed: authentication failed"]
+        );
+        assert_eq!(started_users(&greetd), ["alice"]);
+    }
+
+    #[test]
+    fn unknown_user_is_refused() {
+        let mut ui = HeadlessUI::new(&["mallory"]);
+        let accounts = vec![Account::new("alice").password("hunter2")];
+        let (result, greetd) = run(&test_config(), accounts, &mut ui);
+
+        assert!(matches!(result, Err(GreetLyError::Unauthenticated)));
+        assert_eq!(ui.messages, ["Authentication failed: unknown user"]);
+        assert!(greetd.started().is_empty());
+    }
+
+    #[test]
+    fn conversation_with_second_factor() {
+        let mut ui = HeadlessUI::new(&["alice", "hunter2", "123456"]);
+        let accounts = vec![Account::new("alice")
+            .step(Step::Info("Welcome back".to_owned()))
+            .password("hunter2")
+            .step(Step::Error("Token required".to_owned()))
+            .step(Step::Visible("Token:".to_owned(), "123456".to_owned()))];
+        let (result, greetd) = run(&test_config(), accounts, &mut ui);
+
+        assert!(result.is_ok());
+        assert_eq!(ui.prompts, ["Password:", "Token:"]);
+        assert_eq!(ui.messages, ["Info: Welcome back", "Error: Token required"]);
+        assert_eq!(started_users(&greetd), ["alice"]);
+    }
+
+    #[test]
+    fn start_failure_returns_to_form() {
+        let mut ui = HeadlessUI::new(&["alice", "hunter2", "bob", "secret"]);
+        let accounts = vec![
+            Account::new("alice")
+                .password("hunter2")
+                .start_error("no such file"),
+            Account::new("bob").password("secret"),
+        ];
+        let (result, greetd) = run(&test_config(), accounts, &mut ui);
+
+        assert!(result.is_ok());
+        assert_eq!(ui.messages, ["Login failed: no such file"]);
+        assert_eq!(started_users(&greetd), ["bob"]);
+    }
+
+    #[test]
+    fn default_user_skips_username_prompt() {
+        let mut config = test_config();
+        config.user.default = Some("alice".to_owned());
+        let mut ui = HeadlessUI::new(&["hunter2"]);
+        let accounts = vec![Account::new("alice").password("hunter2")];
+        let (result, greetd) = run(&config, accounts, &mut ui);
+
+        assert!(result.is_ok());
+        assert_eq!(started_users(&greetd), ["alice"]);
+    }
+
+    #[test]
+    fn quit_cancels_login() {
+        let mut ui = HeadlessUI::new(&["alice"]);
+        let accounts = vec![Account::new("alice").password("hunter2")];
+        let (result, greetd) = run(&test_config(), accounts, &mut ui);
+
+        assert!(matches!(result, Err(GreetLyError::Unauthenticated)));
+        assert!(greetd.started().is_empty());
+    }
+
+    #[test]
+    fn lost_connection_reconnects() {
+        let mut ui = HeadlessUI::new(&["ghost", "alice", "hunter2"]);
+        let accounts = vec![
+            Account::new("alice").password("hunter2"),
+            Account::new("ghost").hang_up(),
+        ];
+        let (result, greetd) = run(&test_config(), accounts, &mut ui);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            ui.statuses,
+            [Some("Reconnecting to greetd\u{2026}".to_owned()), None]
+        );
+        assert_eq!(ui.messages, ["Login failed: Connection to greetd was lost"]);
+        assert!(ui.reconnects.is_empty());
+        assert_eq!(started_users(&greetd), ["alice"]);
+    }
+
+    #[test]
+    fn quit_while_reconnecting() {
+        let mut ui = HeadlessUI::new(&["ghost"]);
+        ui.input.extend(vec![
+            LoginAction::Reconnect,
+            LoginAction::Submit("alice".to_owned(), 0),
+            LoginAction::Reconnect,
+            LoginAction::Quit,
+        ]);
+        let greetd = FakeGreetd::spawn(vec![Account::new("ghost").hang_up()]).unwrap();
+        let mut session_ctx = GreeterContext::connect(greetd.path(), None).unwrap();
+        // Nobody can connect once the socket is gone
+        drop(greetd);
+        let result = greet(&test_config(), &[], &mut session_ctx, &mut ui);
+
+        assert!(matches!(result, Err(GreetLyError::Unauthenticated)));
+        assert_eq!(
+            ui.reconnects,
+            [1, 2, 4]
+                .iter()
+                .map(|&secs| Duration::from_secs(secs))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(ui.messages, ["Error: Not connected to greetd, please wait"]);
+        assert!(ui.statuses.iter().all(Option::is_some));
+    }
+
+    /// A config offering the sessions `first` and `second` and remembering
+    /// them in the state file `name`.
+    fn remembering_config(name: &str, state: &str) -> Config {
+        let dir =
+            std::env::temp_dir().join(format!("greetly-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for id in ["first", "second"].iter() {
+            let entry = format!("[Desktop Entry]\nName={0}\nExec={0}\n", id);
+            std::fs::write(dir.join(format!("{}.desktop", id)), entry).unwrap();
+        }
+        std::fs::write(dir.join("state"), state).unwrap();
+
+        let mut config = test_config();
+        config.state_file = dir.join("state");
+        config.session.remember = true;
+        config.session.wayland_sessions = vec![dir];
+        config
+    }
+
+    fn started_commands(greetd: &FakeGreetd) -> Vec<String> {
+        greetd
+            .started()
+            .into_iter()
+            .filter_map(|(_, command)| command.last().cloned())
+            .collect()
+    }
+
+    #[test]
+    fn remembered_session_starts_without_password() {
+        let config = remembering_config("remembered", "[sessions]\nkiosk = \"second\"\n");
+        let mut ui = HeadlessUI::new(&["kiosk"]);
+        let (result, greetd) = run(&config, vec![Account::new("kiosk")], &mut ui);
+
+        assert!(result.is_ok());
+        assert_eq!(started_commands(&greetd), ["second"]);
+        let _ = std::fs::remove_dir_all(config.state_file.parent().unwrap());
+    }
+
+    #[test]
+    fn corrupt_state_file_is_ignored() {
+        let config = remembering_config("corrupt", "[sessions\nkiosk = ");
+        let mut ui = HeadlessUI::new(&["kiosk"]);
+        let (result, greetd) = run(&config, vec![Account::new("kiosk")], &mut ui);
+
+        assert!(result.is_ok());
+        assert_eq!(started_commands(&greetd), ["first"]);
+        // The state is written anew after the login
+        let state = State::load(&config.state_file);
+        assert_eq!(
+            state.sessions.get("kiosk").map(String::as_str),
+            Some("first")
+        );
+        let _ = std::fs::remove_dir_all(config.state_file.parent().unwrap());
+    }
+}
@@ -0,0 +1,162 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use std::fmt;
+use std::io;
+use std::process::Command;
+
+use crate::config::PowerConfig;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PowerAction {
+    PowerOff,
+    Reboot,
+    Suspend,
+    Hibernate,
+}
+
+impl PowerAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PowerOff => "shutdown",
+            Self::Reboot => "reboot",
+            Self::Suspend => "suspend",
+            Self::Hibernate => "hibernate",
+        }
+    }
+
+    pub fn question(self) -> &'static str {
+        match self {
+            Self::PowerOff => "Do you really want to shut down the system?",
+            Self::Reboot => "Do you really want to reboot the system?",
+            Self::Suspend => "Do you really want to suspend the system?",
+            Self::Hibernate => "Do you really want to hibernate the system?",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PowerError {
+    Disabled(PowerAction),
+    Syntax(String, shell_words::ParseError),
+    Spawn(String, io::Error),
+    Failed(String, String),
+}
+
+impl fmt::Display for PowerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Disabled(action) => write!(f, "No command configured to {}", action.label()),
+            Self::Syntax(cmd, err) => write!(f, "Cannot parse command '{}': {}", cmd, err),
+            Self::Spawn(cmd, err) => write!(f, "Cannot run '{}': {}", cmd, err),
+            Self::Failed(cmd, reason) => write!(f, "'{}' failed: {}", cmd, reason),
+        }
+    }
+}
+
+impl PowerConfig {
+    pub fn command(&self, action: PowerAction) -> Option<&str> {
+        let command = match action {
+            PowerAction::PowerOff => self.poweroff.as_deref(),
+            PowerAction::Reboot => self.reboot.as_deref(),
+            PowerAction::Suspend => self.suspend.as_deref(),
+            PowerAction::Hibernate => self.hibernate.as_deref(),
+        };
+
+        command.filter(|cmd| !cmd.trim().is_empty())
+    }
+
+    pub fn is_enabled(&self, action: PowerAction) -> bool {
+        self.command(action).is_some()
+    }
+}
+
+/// Run the configured command for `action` and wait for it to finish.
+pub fn run(config: &PowerConfig, action: PowerAction) -> Result<(), PowerError> {
+    let command = config.command(action).ok_or(PowerError::Disabled(action))?;
+    let argv =
+        shell_words::split(command).map_err(|err| PowerError::Syntax(command.to_owned(), err))?;
+    if argv.is_empty() {
+        return Err(PowerError::Disabled(action));
+    }
+
+    let output = Command::new(&argv[0])
+        .args(&argv[1..])
+        .output()
+        .map_err(|err| PowerError::Spawn(command.to_owned(), err))?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reason = match stderr.trim() {
+        "" => output.status.to_string(),
+        msg => msg.to_owned(),
+    };
+
+    Err(PowerError::Failed(command.to_owned(), reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(command: &str) -> PowerConfig {
+        PowerConfig {
+            reboot: Some(command.to_owned()),
+            ..PowerConfig::default()
+        }
+    }
+
+    fn error(command: &str) -> String {
+        match run(&config(command), PowerAction::Reboot) {
+            Err(err) => err.to_string(),
+            Ok(()) => panic!("'{}' succeeded", command),
+        }
+    }
+
+    #[test]
+    fn empty_commands_disable_actions() {
+        let mut config = config("   ");
+        config.suspend = None;
+        assert_eq!(config.command(PowerAction::Reboot), None);
+        assert_eq!(config.command(PowerAction::Suspend), None);
+        assert!(!config.is_enabled(PowerAction::Reboot));
+        assert_eq!(
+            config.command(PowerAction::PowerOff),
+            Some("systemctl poweroff")
+        );
+        assert_eq!(error("   "), "No command configured to reboot");
+        assert_eq!(error(""), "No command configured to reboot");
+    }
+
+    #[test]
+    fn runs_commands_split_like_a_shell() {
+        assert!(run(&config("true"), PowerAction::Reboot).is_ok());
+        assert!(run(
+            &config("sh -c 'test \"$0\" = \"two words\"' 'two words'"),
+            PowerAction::Reboot
+        )
+        .is_ok());
+        assert!(run(&config("sh -c 'exit 0' extra"), PowerAction::Reboot).is_ok());
+    }
+
+    #[test]
+    fn reports_failed_commands() {
+        assert_eq!(error("false"), "'false' failed: exit status: 1");
+        assert_eq!(
+            error("sh -c 'echo no permission >&2; exit 3'"),
+            "'sh -c 'echo no permission >&2; exit 3'' failed: no permission"
+        );
+        assert!(
+            error("echo 'unterminated").starts_with("Cannot parse command 'echo 'unterminated':")
+        );
+        assert!(error("/nonexistent/poweroff").starts_with("Cannot run '/nonexistent/poweroff':"));
+    }
+}
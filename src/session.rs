@@ -0,0 +1,395 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod desktop;
+
+use crate::config::SessionConfig;
+use desktop::DesktopEntry;
+
+pub const COMMAND_SESSION_ID: &str = "command";
+
+#[derive(Debug)]
+pub enum SessionError {
+    Syntax(String, shell_words::ParseError),
+    Empty,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Syntax(cmd, err) => write!(f, "Cannot parse command '{}': {}", cmd, err),
+            Self::Empty => write!(f, "Session command is empty"),
+        }
+    }
+}
+
+fn split_command(command: &str) -> Result<Vec<String>, SessionError> {
+    let argv =
+        shell_words::split(command).map_err(|err| SessionError::Syntax(command.to_owned(), err))?;
+
+    if argv.is_empty() {
+        Err(SessionError::Empty)
+    } else {
+        Ok(argv)
+    }
+}
+
+/// Prefix `command` with the `wrapper` argv.
+///
+/// If the wrapper ends in `-c` (like `bash -l -c`), the command is passed to
+/// it as a single, properly quoted string instead of separate arguments.
+fn wrap_command(wrapper: &[String], command: Vec<String>) -> Vec<String> {
+    let mut argv = wrapper.to_vec();
+    match wrapper.last() {
+        None => return command,
+        Some(flag) if flag == "-c" => argv.push(shell_words::join(command)),
+        Some(_) => argv.extend(command),
+    }
+
+    argv
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SessionKind {
+    Wayland,
+    X11,
+    Command,
+}
+
+impl SessionKind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Wayland => "wayland",
+            Self::X11 => "x11",
+            Self::Command => COMMAND_SESSION_ID,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Wayland => "Wayland",
+            Self::X11 => "X11",
+            Self::Command => "Command",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// Unique id, the session file name without `.desktop` or `kind/name` if
+    /// that is taken by another kind of session.
+    pub id: String,
+    /// The session file name without `.desktop`.
+    pub desktop: String,
+    pub name: String,
+    pub kind: SessionKind,
+    pub command: Vec<String>,
+    pub desktop_names: Vec<String>,
+}
+
+impl Session {
+    pub fn from_command(command: &str) -> Result<Self, SessionError> {
+        let command = split_command(command)?;
+        let name = Path::new(&command[0])
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| command[0].clone());
+
+        Ok(Self {
+            id: COMMAND_SESSION_ID.to_owned(),
+            desktop: COMMAND_SESSION_ID.to_owned(),
+            name,
+            kind: SessionKind::Command,
+            command,
+            desktop_names: Vec::new(),
+        })
+    }
+
+    fn from_desktop_entry(desktop: String, entry: DesktopEntry, kind: SessionKind) -> Option<Self> {
+        if !entry.is_visible() || !entry.is_installed() {
+            return None;
+        }
+
+        let command = match entry.exec_argv()? {
+            Ok(ref argv) if argv.is_empty() => return None,
+            Ok(argv) => argv,
+            Err(_) => return None,
+        };
+
+        Some(Self {
+            id: desktop.clone(),
+            desktop,
+            name: entry.name?,
+            kind,
+            command,
+            desktop_names: entry.desktop_names,
+        })
+    }
+
+    fn environment(&self) -> Vec<String> {
+        let mut env = Vec::with_capacity(3);
+        match self.kind {
+            SessionKind::Wayland => env.push("XDG_SESSION_TYPE=wayland".to_owned()),
+            SessionKind::X11 => env.push("XDG_SESSION_TYPE=x11".to_owned()),
+            SessionKind::Command => return env,
+        }
+        env.push(format!("XDG_SESSION_DESKTOP={}", self.desktop));
+        if !self.desktop_names.is_empty() {
+            env.push(format!(
+                "XDG_CURRENT_DESKTOP={}",
+                self.desktop_names.join(":")
+            ));
+        }
+
+        env
+    }
+}
+
+fn discover_directory(dir: &Path, kind: SessionKind, sessions: &mut Vec<Session>) {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+            .collect(),
+        Err(_) => return,
+    };
+    entries.sort();
+
+    for path in entries {
+        let desktop = match path.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        // Entries in earlier directories shadow those of the same kind and name
+        if sessions
+            .iter()
+            .any(|session| session.kind == kind && session.desktop == desktop)
+        {
+            continue;
+        }
+
+        let mut session = match DesktopEntry::load(&path)
+            .ok()
+            .and_then(|entry| Session::from_desktop_entry(desktop, entry, kind))
+        {
+            Some(session) => session,
+            None => continue,
+        };
+        // Tell apart sessions sharing a name with another kind or the command
+        if session.id == COMMAND_SESSION_ID || sessions.iter().any(|s| s.id == session.id) {
+            session.id = format!("{}/{}", kind.name(), session.desktop);
+            session.name = format!("{} ({})", session.name, kind.label());
+        }
+        sessions.push(session);
+    }
+}
+
+/// Collect all available sessions.
+///
+/// Sessions from the configured wayland and X11 directories come first,
+/// followed by the configured session command.
+pub fn discover(config: &SessionConfig) -> Result<Vec<Session>, SessionError> {
+    let mut sessions = Vec::new();
+    for dir in config.wayland_sessions.iter() {
+        discover_directory(dir, SessionKind::Wayland, &mut sessions);
+    }
+    for dir in config.x11_sessions.iter() {
+        discover_directory(dir, SessionKind::X11, &mut sessions);
+    }
+    sessions.push(Session::from_command(&config.command)?);
+
+    Ok(sessions)
+}
+
+/// Builds the argv sent to greetd for a session.
+pub struct SessionLauncher {
+    wrapper: Vec<String>,
+    xsession_wrapper: Vec<String>,
+}
+
+fn split_wrapper(wrapper: Option<&str>) -> Result<Vec<String>, SessionError> {
+    match wrapper {
+        Some(wrapper) if !wrapper.trim().is_empty() => split_command(wrapper),
+        _ => Ok(Vec::new()),
+    }
+}
+
+impl SessionLauncher {
+    pub fn new(config: &SessionConfig) -> Result<Self, SessionError> {
+        Ok(Self {
+            wrapper: split_wrapper(config.wrapper.as_deref())?,
+            xsession_wrapper: split_wrapper(config.xsession_wrapper.as_deref())?,
+        })
+    }
+
+    pub fn command(&self, session: &Session) -> Vec<String> {
+        let mut command = session.command.clone();
+
+        let env = session.environment();
+        if !env.is_empty() {
+            command = ["env".to_owned()]
+                .iter()
+                .cloned()
+                .chain(env)
+                .chain(command)
+                .collect();
+        }
+        if session.kind == SessionKind::X11 {
+            command = wrap_command(&self.xsession_wrapper, command);
+        }
+
+        wrap_command(&self.wrapper, command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn session(kind: SessionKind, command: &[&str]) -> Session {
+        Session {
+            id: "i3".to_owned(),
+            desktop: "i3".to_owned(),
+            name: "i3".to_owned(),
+            kind,
+            command: argv(command),
+            desktop_names: vec!["i3".to_owned()],
+        }
+    }
+
+    fn launcher(wrapper: Option<&str>, xsession_wrapper: Option<&str>) -> SessionLauncher {
+        SessionLauncher::new(&SessionConfig {
+            wrapper: wrapper.map(str::to_owned),
+            xsession_wrapper: xsession_wrapper.map(str::to_owned),
+            ..SessionConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn splits_commands_like_a_shell() {
+        assert_eq!(
+            split_command(r#"sh -c 'echo "a b"' c\ d"#).unwrap(),
+            argv(&["sh", "-c", r#"echo "a b""#, "c d"])
+        );
+        assert!(matches!(split_command("  "), Err(SessionError::Empty)));
+        assert!(matches!(
+            split_command("sh 'open"),
+            Err(SessionError::Syntax(..))
+        ));
+    }
+
+    #[test]
+    fn wraps_commands() {
+        let command = argv(&["sway", "--config", "my config"]);
+        assert_eq!(wrap_command(&[], command.clone()), command);
+        assert_eq!(
+            wrap_command(&argv(&["dbus-run-session"]), command.clone()),
+            argv(&["dbus-run-session", "sway", "--config", "my config"])
+        );
+        assert_eq!(
+            wrap_command(&argv(&["bash", "-l", "-c"]), command),
+            argv(&["bash", "-l", "-c", "sway --config 'my config'"])
+        );
+    }
+
+    #[test]
+    fn builds_session_commands() {
+        let command = session(SessionKind::Command, &["fish"]);
+        assert_eq!(launcher(None, None).command(&command), argv(&["fish"]));
+
+        let wayland = session(SessionKind::Wayland, &["sway"]);
+        assert_eq!(
+            launcher(Some("bash -l -c"), None).command(&wayland),
+            argv(&[
+                "bash",
+                "-l",
+                "-c",
+                "env 'XDG_SESSION_TYPE=wayland' 'XDG_SESSION_DESKTOP=i3' 'XDG_CURRENT_DESKTOP=i3' sway"
+            ])
+        );
+
+        // The X11 wrapper runs inside the general one
+        let x11 = session(SessionKind::X11, &["i3"]);
+        assert_eq!(
+            launcher(Some("dbus-run-session"), Some("startx /usr/bin/env")).command(&x11),
+            argv(&[
+                "dbus-run-session",
+                "startx",
+                "/usr/bin/env",
+                "env",
+                "XDG_SESSION_TYPE=x11",
+                "XDG_SESSION_DESKTOP=i3",
+                "XDG_CURRENT_DESKTOP=i3",
+                "i3"
+            ])
+        );
+        assert_eq!(launcher(None, Some("")).command(&x11)[0], "env");
+    }
+
+    /// Write `.desktop` files named after `entries` into a new directory.
+    fn session_dir(name: &str, entries: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("greetly-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, name) in entries {
+            let content = format!("[Desktop Entry]\nName={}\nExec={}\n", name, file);
+            fs::write(dir.join(format!("{}.desktop", file)), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn discovers_sessions_by_kind() {
+        let wayland = session_dir("wayland", &[("gnome", "GNOME"), ("sway", "Sway")]);
+        let local = session_dir("local", &[("sway", "My Sway")]);
+        let x11 = session_dir(
+            "x11",
+            &[("command", "Command"), ("gnome", "GNOME"), ("i3", "i3")],
+        );
+        let config = SessionConfig {
+            command: "/bin/fish".to_owned(),
+            wayland_sessions: vec![local.clone(), wayland.clone()],
+            x11_sessions: vec![x11.clone()],
+            ..SessionConfig::default()
+        };
+
+        let sessions = discover(&config).unwrap();
+        let found: Vec<_> = sessions
+            .iter()
+            .map(|s| (s.id.as_str(), s.name.as_str(), s.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("sway", "My Sway", SessionKind::Wayland),
+                ("gnome", "GNOME", SessionKind::Wayland),
+                ("x11/command", "Command (X11)", SessionKind::X11),
+                ("x11/gnome", "GNOME (X11)", SessionKind::X11),
+                ("i3", "i3", SessionKind::X11),
+                ("command", "fish", SessionKind::Command),
+            ]
+        );
+        assert!(launcher(None, None)
+            .command(&sessions[3])
+            .contains(&"XDG_SESSION_DESKTOP=gnome".to_owned()));
+
+        for dir in [wayland, local, x11].iter() {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
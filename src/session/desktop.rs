@@ -0,0 +1,198 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+const DESKTOP_ENTRY_GROUP: &str = "[Desktop Entry]";
+
+/// The keys of a `.desktop` file relevant for launching a session.
+#[derive(Debug, Default)]
+pub struct DesktopEntry {
+    pub name: Option<String>,
+    pub exec: Option<String>,
+    pub try_exec: Option<String>,
+    pub hidden: bool,
+    pub no_display: bool,
+    pub desktop_names: Vec<String>,
+}
+
+fn parse_bool(value: &str) -> bool {
+    value == "true"
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+fn is_executable(path: &Path) -> bool {
+    match fs::metadata(path) {
+        Ok(meta) => meta.is_file() && meta.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+impl DesktopEntry {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut entry = Self::default();
+        let mut in_group = false;
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_group = line == DESKTOP_ENTRY_GROUP;
+                continue;
+            }
+            if !in_group {
+                continue;
+            }
+
+            // Localized keys like `Name[de]` are ignored
+            let (key, value) = match line.find('=') {
+                Some(idx) => (line[..idx].trim(), line[idx + 1..].trim()),
+                None => continue,
+            };
+            match key {
+                "Name" => entry.name = Some(unescape(value)),
+                "Exec" => entry.exec = Some(value.to_owned()),
+                "TryExec" => entry.try_exec = Some(unescape(value)),
+                "Hidden" => entry.hidden = parse_bool(value),
+                "NoDisplay" => entry.no_display = parse_bool(value),
+                "DesktopNames" => {
+                    entry.desktop_names = value
+                        .split(';')
+                        .filter(|name| !name.is_empty())
+                        .map(unescape)
+                        .collect()
+                }
+                _ => {}
+            }
+        }
+
+        entry
+    }
+
+    /// Whether the entry should be offered to the user.
+    pub fn is_visible(&self) -> bool {
+        !self.hidden && !self.no_display && self.name.is_some() && self.exec.is_some()
+    }
+
+    /// Whether the program given in `TryExec` (if any) is installed.
+    pub fn is_installed(&self) -> bool {
+        let program = match self.try_exec {
+            Some(ref program) => PathBuf::from(program),
+            None => return true,
+        };
+
+        if program.is_absolute() {
+            return is_executable(&program);
+        }
+
+        match env::var_os("PATH") {
+            Some(paths) => env::split_paths(&paths).any(|dir| is_executable(&dir.join(&program))),
+            None => false,
+        }
+    }
+
+    /// Split the `Exec` key into its arguments, dropping field codes like `%f`.
+    pub fn exec_argv(&self) -> Option<Result<Vec<String>, shell_words::ParseError>> {
+        let exec = unescape(self.exec.as_ref()?);
+        let argv = shell_words::split(&exec).map(|argv| {
+            argv.into_iter()
+                .filter_map(|arg| match arg.as_str() {
+                    "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%i" | "%c" | "%k"
+                    | "%v" | "%m" => None,
+                    _ => Some(arg.replace("%%", "%")),
+                })
+                .collect()
+        });
+
+        Some(argv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_desktop_entry_group() {
+        let entry = DesktopEntry::parse(
+            "# comment\n[Other]\nName=Wrong\n\n[Desktop Entry]\nName = My\\sDesktop\nName[de]=Mein\nExec=start\nDesktopNames=My;Base;\n",
+        );
+        assert_eq!(entry.name.as_deref(), Some("My Desktop"));
+        assert_eq!(entry.exec.as_deref(), Some("start"));
+        assert_eq!(entry.desktop_names, vec!["My", "Base"]);
+        assert!(entry.is_visible());
+    }
+
+    #[test]
+    fn unescapes_values() {
+        assert_eq!(unescape(r"a\sb\tc\\d\;e\"), "a b\tc\\d;e\\");
+    }
+
+    #[test]
+    fn drops_field_codes_from_exec() {
+        let entry = DesktopEntry::parse(
+            "[Desktop Entry]\nExec=\"/opt/my app/run\" --name %c %U 100%% %f\n",
+        );
+        assert_eq!(
+            entry.exec_argv().unwrap().unwrap(),
+            vec!["/opt/my app/run", "--name", "100%"]
+        );
+        assert!(DesktopEntry::parse("[Desktop Entry]\nExec='open\n")
+            .exec_argv()
+            .unwrap()
+            .is_err());
+        assert!(DesktopEntry::default().exec_argv().is_none());
+    }
+
+    #[test]
+    fn hides_unavailable_entries() {
+        let visible = "[Desktop Entry]\nName=A\nExec=a\n";
+        assert!(!DesktopEntry::parse(&format!("{}Hidden=true\n", visible)).is_visible());
+        assert!(!DesktopEntry::parse(&format!("{}NoDisplay=true\n", visible)).is_visible());
+        assert!(DesktopEntry::parse(&format!("{}NoDisplay=false\n", visible)).is_visible());
+        assert!(!DesktopEntry::parse("[Desktop Entry]\nExec=a\n").is_visible());
+
+        let installed = |try_exec: &str| {
+            DesktopEntry::parse(&format!("{}TryExec={}\n", visible, try_exec)).is_installed()
+        };
+        assert!(DesktopEntry::parse(visible).is_installed());
+        assert!(installed("/bin/sh"));
+        assert!(!installed("/etc/passwd"));
+        assert!(!installed("/nonexistent/greetly-session"));
+        assert!(!installed("greetly-no-such-session"));
+    }
+}
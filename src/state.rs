@@ -0,0 +1,95 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+/// Data kept between runs of the greeter.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct State {
+    pub last_user: Option<String>,
+    /// Id of the last session started by each user.
+    pub sessions: BTreeMap<String, String>,
+}
+
+impl State {
+    /// Load the state from `path`, a missing or unreadable file yields an empty state.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Replace the file at `path` with the current state.
+    ///
+    /// The state is written to a temporary file first and then renamed, so
+    /// the file is never left half written.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+
+        fs::rename(&tmp_path, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn loads_missing_or_corrupt_state_as_empty() {
+        let path = env::temp_dir().join(format!("greetly-test-{}-state", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(State::load(&path).last_user.is_none());
+
+        fs::write(&path, "last_user = [").unwrap();
+        let state = State::load(&path);
+        assert!(state.last_user.is_none() && state.sessions.is_empty());
+
+        // Unknown keys from other versions do not lose the rest
+        fs::write(&path, "last_user = \"alice\"\nfuture = 1\n").unwrap();
+        assert_eq!(State::load(&path).last_user.as_deref(), Some("alice"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn saves_and_loads_state() {
+        let dir = env::temp_dir().join(format!("greetly-test-{}-state-dir", std::process::id()));
+        let path = dir.join("nested").join("state");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut state = State {
+            last_user: Some("alice".to_owned()),
+            ..State::default()
+        };
+        state.sessions.insert("alice".to_owned(), "sway".to_owned());
+        state.save(&path).unwrap();
+
+        let loaded = State::load(&path);
+        assert_eq!(loaded.last_user.as_deref(), Some("alice"));
+        assert_eq!(loaded.sessions, state.sessions);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
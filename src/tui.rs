@@ -8,11 +8,24 @@
 
 use std::fmt;
 use std::io;
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use crate::power::PowerAction;
+use crate::session::Session;
+use crate::users::User;
 
 pub mod components;
+mod event;
 mod greeter;
+mod keymap;
+pub mod layout;
+mod terminal;
+mod theme;
 
 pub use greeter::TerminalUI;
+pub use keymap::{Action, Keymap};
+pub use theme::Theme;
 
 #[macro_export]
 macro_rules! draw2tty {
@@ -25,13 +38,33 @@ macro_rules! draw2tty {
 pub enum LoginAction {
     Quit,
     Cancel,
-    Submit(String),
+    Submit(String, usize),
+    PowerOff,
+    Reboot,
+    Suspend,
+    Hibernate,
+    /// greetd hung up while waiting for input.
+    Disconnected,
+    /// The delay given to `LoginUI::schedule_reconnect` has passed.
+    Reconnect,
+}
+
+impl From<PowerAction> for LoginAction {
+    fn from(action: PowerAction) -> Self {
+        match action {
+            PowerAction::PowerOff => Self::PowerOff,
+            PowerAction::Reboot => Self::Reboot,
+            PowerAction::Suspend => Self::Suspend,
+            PowerAction::Hibernate => Self::Hibernate,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum LoginError {
     NotATTY,
     IoError(io::Error),
+    System(nix::Error),
 }
 
 impl fmt::Display for LoginError {
@@ -39,6 +72,7 @@ impl fmt::Display for LoginError {
         match self {
             Self::NotATTY => write!(f, "Stdout is not a TTY"),
             Self::IoError(e) => write!(f, "{}", e),
+            Self::System(e) => write!(f, "{}", e),
         }
     }
 }
@@ -49,16 +83,39 @@ impl From<io::Error> for LoginError {
     }
 }
 
+impl From<nix::Error> for LoginError {
+    fn from(err: nix::Error) -> Self {
+        LoginError::System(err)
+    }
+}
+
 pub trait GreetUI {
     fn set_prompt(&mut self, prompt: &str);
     fn set_secret_prompt(&mut self, prompt: &str);
     fn show_info_message(&mut self, message: &str);
     fn show_error_message(&mut self, message: &str);
     fn show_authentication_failure(&mut self, reason: &str);
+    fn show_session_failure(&mut self, reason: &str);
+}
+
+/// Interface the login loop uses on top of the greetd callbacks.
+pub trait LoginUI: GreetUI {
+    /// Wait for the next action, `socket` is watched for greetd hanging up
+    /// unless it is negative.
+    fn handle_input(&mut self, socket: RawFd) -> Result<LoginAction, LoginError>;
+    fn set_sessions(&mut self, sessions: &[Session], selected: usize);
+    fn set_users(&mut self, users: &[User]);
+    fn set_user(&mut self, user: Option<&str>);
+    fn select_session(&mut self, index: usize);
+    fn selected_session(&self) -> usize;
+    /// Show `status` right away until it is replaced or cleared with `None`.
+    fn set_status(&mut self, status: Option<&str>);
+    /// Make `handle_input` return `LoginAction::Reconnect` once `after` has passed.
+    fn schedule_reconnect(&mut self, after: Duration);
 }
 
 trait Draw<T: io::Write> {
-    fn draw(&self, tty: &mut T, base: (u16, u16), focused: bool);
+    fn draw(&self, tty: &mut T, theme: &Theme, base: (u16, u16), focused: bool);
 }
 
 trait KeyboardInput<T: io::Write>: Draw<T> {
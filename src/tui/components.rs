@@ -6,14 +6,22 @@
 // that was distributed with this source code.
 //
 
+mod clock;
+mod completion;
 mod container;
+mod dialog;
 mod form;
 mod input;
 mod label;
 mod message;
+mod selector;
 
+pub use clock::Clock;
+pub use completion::Completion;
 pub use container::{BorderType, Container};
-pub use form::{FormElement, LoginForm};
+pub use dialog::Dialog;
+pub use form::{FormElement, FormLayout, LoginForm};
 pub use input::TextInput;
 pub use label::Label;
 pub use message::Message;
+pub use selector::Selector;
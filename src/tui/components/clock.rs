@@ -0,0 +1,144 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use nix::libc;
+use std::ffi::CString;
+use std::mem;
+use std::ptr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use termion::cursor;
+
+use std::io::Write;
+
+use crate::tui::layout::{Align, Rect};
+use crate::tui::theme::Role;
+use crate::tui::{Draw, Theme};
+
+use crate::draw2tty;
+
+extern "C" {
+    fn tzset();
+    fn strftime(
+        s: *mut libc::c_char,
+        max: libc::size_t,
+        format: *const libc::c_char,
+        tm: *const libc::tm,
+    ) -> libc::size_t;
+}
+
+/// The local time written like `strftime(3)` does.
+fn format_time(format: &str) -> String {
+    let format = match CString::new(format) {
+        Ok(format) => format,
+        Err(_) => return String::new(),
+    };
+
+    let mut buf = [0u8; 256];
+    let len = unsafe {
+        tzset();
+        let now = libc::time(ptr::null_mut());
+        let mut tm: libc::tm = mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return String::new();
+        }
+        strftime(
+            buf.as_mut_ptr() as *mut libc::c_char,
+            buf.len(),
+            format.as_ptr(),
+            &tm,
+        )
+    };
+
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+/// The current date and time centered in a row.
+pub struct Clock {
+    format: String,
+    area: Rect,
+}
+
+impl Clock {
+    pub fn new(format: &str) -> Self {
+        Self {
+            format: format.to_owned(),
+            area: Rect::default(),
+        }
+    }
+
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = area;
+    }
+
+    /// Time until the shown text changes.
+    pub fn next_tick(&self) -> Duration {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        // Only formats showing seconds need to be redrawn every second
+        let period = if ["%S", "%T", "%X", "%c", "%r", "%s"]
+            .iter()
+            .any(|spec| self.format.contains(spec))
+        {
+            1000
+        } else {
+            60_000
+        };
+        let millis = now.as_millis() as u64;
+
+        Duration::from_millis(period - millis % period)
+    }
+}
+
+impl<T: Write> Draw<T> for Clock {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), _focused: bool) {
+        if self.area.width == 0 || self.area.height == 0 {
+            return;
+        }
+
+        let text: String = format_time(&self.format)
+            .chars()
+            .take(self.area.width as usize)
+            .collect();
+        let len = text.chars().count() as u16;
+        let (x, y) = self
+            .area
+            .align((len, 1), Align::Center, Align::Start)
+            .position();
+
+        draw2tty!(
+            tty,
+            "{}{: <6$}{}{}{}{}",
+            cursor::Goto(origin.0 + self.area.x, origin.1 + self.area.y),
+            "",
+            cursor::Goto(origin.0 + x, origin.1 + y),
+            theme.paint(Role::Label),
+            text,
+            theme.plain(),
+            self.area.width as usize
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_local_time() {
+        assert_eq!(format_time("%%"), "%");
+        assert_eq!(format_time("%Y").len(), 4);
+        assert_eq!(format_time("a\0b"), "");
+    }
+
+    #[test]
+    fn ticks_at_the_next_change() {
+        assert!(Clock::new("%H:%M").next_tick() <= Duration::from_secs(60));
+        assert!(Clock::new("%H:%M:%S").next_tick() <= Duration::from_secs(1));
+    }
+}
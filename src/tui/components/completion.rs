@@ -0,0 +1,306 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use termion::cursor;
+
+use crate::tui::components::label::truncate_text;
+use crate::tui::components::{BorderType, Container};
+
+use std::io::Write;
+
+use crate::tui::theme::Role;
+use crate::tui::{Draw, Theme};
+
+use crate::draw2tty;
+
+pub struct Completion {
+    candidates: Vec<(String, String)>,
+    matches: Vec<usize>,
+    selected: Option<usize>,
+    position: (u16, u16),
+    width: u16,
+    rows: u16,
+    border: BorderType,
+}
+
+impl Completion {
+    pub fn new(position: (u16, u16), width: u16, rows: u16, border: BorderType) -> Self {
+        Self {
+            candidates: Vec::new(),
+            matches: Vec::new(),
+            selected: None,
+            position,
+            width,
+            rows,
+            border,
+        }
+    }
+
+    pub fn set_position(&mut self, position: (u16, u16)) {
+        self.position = position;
+    }
+
+    pub fn set_width(&mut self, width: u16) {
+        self.width = width;
+    }
+
+    pub fn set_rows(&mut self, rows: u16) {
+        self.rows = rows;
+    }
+
+    /// Set the list of `(name, description)` pairs to complete from.
+    pub fn set_candidates(&mut self, candidates: Vec<(String, String)>) {
+        self.candidates = candidates;
+        self.update("");
+    }
+
+    /// Filter the candidates by `input`.
+    ///
+    /// Names are matched by prefix, descriptions anywhere ignoring case.
+    pub fn update(&mut self, input: &str) {
+        let needle = input.to_lowercase();
+        self.matches = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, (name, description))| {
+                name.starts_with(input) || description.to_lowercase().contains(&needle)
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        self.selected = None;
+    }
+
+    pub fn has_matches(&self) -> bool {
+        !self.matches.is_empty()
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    /// Select the next match, wrapping around to the first one.
+    pub fn select_next(&mut self) {
+        if self.matches.is_empty() {
+            return;
+        }
+        self.selected = Some(match self.selected {
+            None => 0,
+            Some(idx) => (idx + 1) % self.matches.len(),
+        });
+    }
+
+    /// Select the previous match, the first one goes back to the input.
+    pub fn select_prev(&mut self) {
+        self.selected = match self.selected {
+            None | Some(0) => None,
+            Some(idx) => Some(idx - 1),
+        };
+    }
+
+    /// The name of the selected candidate.
+    pub fn selection(&self) -> Option<&str> {
+        self.selected
+            .map(|idx| self.candidates[self.matches[idx]].0.as_str())
+    }
+
+    /// The name of the selected candidate or the first one starting with `input`.
+    pub fn suggestion(&self, input: &str) -> Option<&str> {
+        self.selection().or_else(|| {
+            self.matches
+                .iter()
+                .map(|&idx| self.candidates[idx].0.as_str())
+                .find(|name| name.starts_with(input))
+        })
+    }
+
+    /// The longest common prefix of all names starting with `input`.
+    pub fn common_prefix(&self, input: &str) -> Option<String> {
+        let mut names = self
+            .matches
+            .iter()
+            .map(|&idx| self.candidates[idx].0.as_str())
+            .filter(|name| name.starts_with(input));
+
+        let mut prefix: Vec<char> = names.next()?.chars().collect();
+        for name in names {
+            let common = prefix
+                .iter()
+                .zip(name.chars())
+                .take_while(|(a, b)| *a == b)
+                .count();
+            prefix.truncate(common);
+        }
+
+        Some(prefix.into_iter().collect())
+    }
+}
+
+impl<T: Write> Draw<T> for Completion {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), focused: bool) {
+        let new_origin = (origin.0 + self.position.0, origin.1 + self.position.1);
+        let (x, y) = new_origin;
+        if self.rows == 0 {
+            return;
+        }
+
+        // Blank the whole area, the list shrinks while typing
+        for row in 0..self.rows + 2 {
+            draw2tty!(
+                tty,
+                "{}{: <2$}",
+                cursor::Goto(x, y + row),
+                "",
+                self.width as usize
+            );
+        }
+        if !focused || self.matches.is_empty() {
+            return;
+        }
+
+        // Scroll the list to keep the selection visible
+        let rows = self.rows as usize;
+        let first = match self.selected {
+            Some(idx) if idx >= rows => idx + 1 - rows,
+            _ => 0,
+        };
+        let shown = self.matches.len().min(rows) as u16;
+
+        let container = Container::new(self.border, (0, 0), (self.width, shown + 2));
+        container.draw(tty, theme, new_origin, false);
+
+        let text_len = (self.width as usize).saturating_sub(4);
+        for (row, &idx) in self.matches.iter().skip(first).take(rows).enumerate() {
+            let (name, description) = &self.candidates[idx];
+            let text = if description.is_empty() || description == name {
+                name.clone()
+            } else {
+                format!("{} ({})", name, description)
+            };
+            let text = truncate_text(&text, Some(text_len));
+
+            let role = if self.selected == Some(first + row) {
+                Role::Selection
+            } else {
+                Role::Label
+            };
+            draw2tty!(
+                tty,
+                "{}{} {: <4$} {}",
+                cursor::Goto(x + 1, y + 1 + row as u16),
+                theme.paint(role),
+                text,
+                theme.plain(),
+                text_len
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completion(names: &[(&str, &str)]) -> Completion {
+        let mut completion = Completion::new((0, 0), 20, 5, BorderType::Ascii);
+        let candidates = names
+            .iter()
+            .map(|&(name, description)| (name.to_owned(), description.to_owned()))
+            .collect();
+        completion.set_candidates(candidates);
+        completion
+    }
+
+    fn matches(completion: &Completion) -> Vec<&str> {
+        completion
+            .matches
+            .iter()
+            .map(|&idx| completion.candidates[idx].0.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn matches_names_by_prefix_and_descriptions_anywhere() {
+        let mut completion = completion(&[
+            ("alice", "Alice Liddell"),
+            ("albert", ""),
+            ("bob", "Bob Allen"),
+        ]);
+        assert_eq!(matches(&completion), ["alice", "albert", "bob"]);
+
+        completion.update("al");
+        assert_eq!(matches(&completion), ["alice", "albert", "bob"]);
+        completion.update("ali");
+        assert_eq!(matches(&completion), ["alice"]);
+        completion.update("Al");
+        assert_eq!(matches(&completion), ["alice", "bob"]);
+    }
+
+    #[test]
+    fn completes_the_common_prefix() {
+        let mut completion = completion(&[("alice", ""), ("alina", ""), ("bob", "Alf")]);
+        completion.update("a");
+        assert_eq!(completion.common_prefix("a").as_deref(), Some("ali"));
+        completion.update("alic");
+        assert_eq!(completion.common_prefix("alic").as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn suggests_the_selection_or_first_name() {
+        let mut completion = completion(&[("bob", "Bob Alien"), ("alice", ""), ("alina", "")]);
+        completion.update("al");
+        // bob only matches by description
+        assert_eq!(completion.suggestion("al"), Some("alice"));
+
+        completion.select_next();
+        assert_eq!(completion.suggestion("al"), Some("bob"));
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut completion = completion(&[("alice", ""), ("bob", ""), ("carol", "")]);
+        assert_eq!(completion.selection(), None);
+
+        completion.select_next();
+        assert_eq!(completion.selection(), Some("alice"));
+        completion.select_next();
+        completion.select_next();
+        assert_eq!(completion.selection(), Some("carol"));
+        completion.select_next();
+        assert_eq!(completion.selection(), Some("alice"));
+
+        completion.select_next();
+        completion.select_prev();
+        assert_eq!(completion.selection(), Some("alice"));
+        // Back to the input
+        completion.select_prev();
+        assert!(!completion.is_selected());
+        completion.select_prev();
+        assert!(!completion.is_selected());
+
+        completion.select_next();
+        completion.update("b");
+        assert!(!completion.is_selected());
+    }
+
+    #[test]
+    fn handles_no_matches() {
+        let empty = completion(&[]);
+        assert!(!empty.has_matches());
+        assert_eq!(empty.suggestion(""), None);
+
+        let mut completion = completion(&[("alice", ""), ("bob", "")]);
+        completion.update("x");
+        assert!(!completion.has_matches());
+        assert_eq!(completion.suggestion("x"), None);
+        assert_eq!(completion.common_prefix("x"), None);
+
+        completion.select_next();
+        assert_eq!(completion.selection(), None);
+    }
+}
@@ -6,12 +6,19 @@
 // that was distributed with this source code.
 //
 
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::fmt;
 use std::iter;
+use std::str::FromStr;
 use termion::cursor;
 
 use std::io::Write;
 
-use crate::tui::Draw;
+use crate::tui::components::label::truncate_text;
+use crate::tui::layout::{Align, Margin, Rect};
+use crate::tui::theme::Role;
+use crate::tui::{Draw, Theme};
 
 use crate::draw2tty;
 
@@ -45,11 +52,13 @@ struct BorderCharacters {
     south: char,
     southwest: char,
     west: char,
+    /// Ends of separators on the west and east side.
+    joints: (char, char),
 }
 
 impl BorderCharacters {
     #[allow(clippy::too_many_arguments)]
-    pub fn new(
+    fn new(
         northwest: char,
         north: char,
         northeast: char,
@@ -68,8 +77,14 @@ impl BorderCharacters {
             south,
             southwest,
             west,
+            joints: (west, east),
         }
     }
+
+    fn with_joints(mut self, west: char, east: char) -> Self {
+        self.joints = (west, east);
+        self
+    }
 }
 
 impl Default for BorderCharacters {
@@ -83,51 +98,226 @@ impl Default for BorderCharacters {
             south: ' ',
             southwest: ' ',
             west: ' ',
+            joints: (' ', ' '),
         }
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum BorderType {
-    //Plain,
-    //Ascii,
-    //Dos,
-    //DosDouble,
+    Plain,
+    Ascii,
+    Dos,
+    DosDouble,
     Unicode,
-    //UnicodeBold,
-    //UnicodeDouble,
+    UnicodeBold,
+    UnicodeDouble,
+    Rounded,
+    /// Corners and sides clockwise from the top left corner.
+    Custom([char; 8]),
+}
+
+const BORDER_NAMES: [(&str, BorderType); 8] = [
+    ("plain", BorderType::Plain),
+    ("ascii", BorderType::Ascii),
+    ("dos", BorderType::Dos),
+    ("dos-double", BorderType::DosDouble),
+    ("unicode", BorderType::Unicode),
+    ("unicode-bold", BorderType::UnicodeBold),
+    ("unicode-double", BorderType::UnicodeDouble),
+    ("rounded", BorderType::Rounded),
+];
+
+impl BorderType {
+    fn is_ascii(&self) -> bool {
+        match self {
+            Self::Plain | Self::Ascii => true,
+            Self::Custom(chars) => chars.iter().all(char::is_ascii),
+            _ => false,
+        }
+    }
+
+    /// This border, or `Ascii` if it cannot be shown without UTF-8.
+    pub fn fallback(self, utf8: bool) -> Self {
+        if utf8 || self.is_ascii() {
+            self
+        } else {
+            Self::Ascii
+        }
+    }
+}
+
+impl fmt::Display for BorderType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Custom(chars) => f.write_str(&chars.iter().collect::<String>()),
+            border => {
+                let (name, _) = BORDER_NAMES
+                    .iter()
+                    .find(|(_, b)| b == border)
+                    .expect("BorderType has no name");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl FromStr for BorderType {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if let Some((_, border)) = BORDER_NAMES.iter().find(|(n, _)| *n == name) {
+            return Ok(*border);
+        }
+
+        let chars: Vec<char> = name.chars().collect();
+        match chars.as_slice() {
+            &[nw, n, ne, e, se, s, sw, w] => Ok(Self::Custom([nw, n, ne, e, se, s, sw, w])),
+            _ => Err(format!(
+                "unknown border `{}`, expected a border name or eight characters",
+                name
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BorderType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+struct Title {
+    text: String,
+    align: Align,
+    role: Role,
 }
 
 pub struct Container {
     position: (u16, u16),
     size: (u16, u16),
     characters: BorderCharacters,
+    title: Option<Title>,
+    padding: (u16, u16),
+    separators: Vec<u16>,
 }
 
 impl Container {
     pub fn new(border: BorderType, position: (u16, u16), size: (u16, u16)) -> Self {
         let characters = match border {
-            //BorderType::Plain => BorderCharacters::default(),
-            //BorderType::Ascii => BorderCharacters::new('+', '-', '+', '|', '+', '-', '+', '|'),
-            //BorderType::Dos => BorderCharacters::new('┌', '─', '┐', '│', '┘', '─', '└', '│'),
-            //BorderType::DosDouble => BorderCharacters::new('╔', '═', '╗', '║', '╝', '═', '╚', '║'),
-            BorderType::Unicode => BorderCharacters::new('┌', '─', '┐', '│', '┘', '─', '└', '│'),
-            //BorderType::UnicodeBold => BorderCharacters::new('┏', '━', '┓', '┃', '┛', '━', '┗', '┃'),
-            //BorderType::UnicodeDouble => BorderCharacters::new('╔', '═', '╗', '║', '╝', '═', '╚', '║'),
+            BorderType::Plain => BorderCharacters::default(),
+            BorderType::Ascii => {
+                BorderCharacters::new('+', '-', '+', '|', '+', '-', '+', '|').with_joints('+', '+')
+            }
+            BorderType::Dos => {
+                BorderCharacters::new('┌', '─', '┐', '│', '┘', '─', '└', '│').with_joints('├', '┤')
+            }
+            BorderType::DosDouble => {
+                BorderCharacters::new('╔', '═', '╗', '║', '╝', '═', '╚', '║').with_joints('╠', '╣')
+            }
+            BorderType::Unicode => {
+                BorderCharacters::new('┌', '─', '┐', '│', '┘', '─', '└', '│').with_joints('├', '┤')
+            }
+            BorderType::UnicodeBold => {
+                BorderCharacters::new('┏', '━', '┓', '┃', '┛', '━', '┗', '┃').with_joints('┣', '┫')
+            }
+            BorderType::UnicodeDouble => {
+                BorderCharacters::new('╔', '═', '╗', '║', '╝', '═', '╚', '║').with_joints('╠', '╣')
+            }
+            BorderType::Rounded => {
+                BorderCharacters::new('╭', '─', '╮', '│', '╯', '─', '╰', '│').with_joints('├', '┤')
+            }
+            BorderType::Custom([nw, n, ne, e, se, s, sw, w]) => {
+                BorderCharacters::new(nw, n, ne, e, se, s, sw, w)
+            }
         };
 
         Self {
             position,
             size,
             characters,
+            title: None,
+            padding: (0, 0),
+            separators: Vec::new(),
         }
     }
+
+    pub fn set_size(&mut self, size: (u16, u16)) {
+        self.size = size;
+    }
+
+    pub fn area(&self) -> Rect {
+        Rect::new(self.position, self.size)
+    }
+
+    /// Show `text` in the top border in the style of `role`.
+    pub fn set_title(&mut self, text: &str, align: Align, role: Role) {
+        self.title = Some(Title {
+            text: text.to_owned(),
+            align,
+            role,
+        });
+    }
+
+    /// Keep `horizontal` columns and `vertical` rows free inside the border.
+    pub fn set_padding(&mut self, horizontal: u16, vertical: u16) {
+        self.padding = (horizontal, vertical);
+    }
+
+    /// Draw lines joining the side borders at `rows`, counted from the top border.
+    pub fn set_separators(&mut self, rows: Vec<u16>) {
+        self.separators = rows;
+    }
+
+    /// Position and size of the area inside the border and padding.
+    pub fn inner(&self) -> Rect {
+        let (pad_x, pad_y) = self.padding;
+        self.area().inset(Margin::symmetric(1 + pad_x, 1 + pad_y))
+    }
+
+    fn draw_title<T: Write>(&self, tty: &mut T, theme: &Theme, (x, y): (u16, u16)) {
+        let title = match self.title {
+            Some(ref title) if !title.text.is_empty() => title,
+            _ => return,
+        };
+
+        // Keep a piece of the border next to the corners
+        let space = self.size.0.saturating_sub(4) as usize;
+        if space < 3 {
+            return;
+        }
+        let text = truncate_text(&title.text, Some(space - 2));
+        let len = text.chars().count() as u16 + 2;
+        let offset = 2 + title.align.offset(len, self.size.0 - 4);
+
+        draw2tty!(
+            tty,
+            "{} {}{}{} ",
+            cursor::Goto(x + offset, y),
+            theme.paint(title.role),
+            text,
+            theme.plain()
+        );
+    }
 }
 
 impl<T: Write> Draw<T> for Container {
-    fn draw(&self, tty: &mut T, origin: (u16, u16), _focused: bool) {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), focused: bool) {
         let (x, y) = (origin.0 + self.position.0, origin.1 + self.position.1);
         let (width, height) = self.size;
         let c = &self.characters;
+        if width < 2 || height < 2 {
+            return;
+        }
+
+        let role = if focused {
+            Role::BorderFocused
+        } else {
+            Role::Border
+        };
+        draw2tty!(tty, "{}", theme.paint(role));
 
         draw_horiz_line!(tty, width, (x, y), c.northwest, c.north, c.northeast);
         draw_vert_line!(tty, height - 2, (x, y + 1), c.west);
@@ -140,5 +330,102 @@ impl<T: Write> Draw<T> for Container {
             c.south,
             c.southeast
         );
+        for &row in self
+            .separators
+            .iter()
+            .filter(|&&row| row > 0 && row < height - 1)
+        {
+            let (west, east) = c.joints;
+            draw_horiz_line!(tty, width, (x, y + row), west, c.north, east);
+        }
+        draw2tty!(tty, "{}", theme.plain());
+
+        self.draw_title(tty, theme, (x, y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_borders() {
+        assert_eq!("rounded".parse(), Ok(BorderType::Rounded));
+        assert_eq!("dos-double".parse(), Ok(BorderType::DosDouble));
+        assert_eq!(
+            "*=*!*=*!".parse(),
+            Ok(BorderType::Custom(['*', '=', '*', '!', '*', '=', '*', '!']))
+        );
+        assert!("++".parse::<BorderType>().is_err());
+        assert!("square".parse::<BorderType>().is_err());
+    }
+
+    #[test]
+    fn falls_back_to_ascii() {
+        assert_eq!(BorderType::Rounded.fallback(true), BorderType::Rounded);
+        assert_eq!(BorderType::Rounded.fallback(false), BorderType::Ascii);
+        assert_eq!(BorderType::Plain.fallback(false), BorderType::Plain);
+
+        let custom: BorderType = "#=#|#=#|".parse().unwrap();
+        assert_eq!(custom.fallback(false), custom);
+        let custom: BorderType = "╭─╮│╯─╰│".parse().unwrap();
+        assert_eq!(custom.fallback(false), BorderType::Ascii);
+    }
+
+    /// The drawn characters without escape sequences.
+    fn drawn(container: &Container) -> String {
+        let mut out = Vec::new();
+        container.draw(&mut out, &Theme::default(), (1, 1), false);
+
+        let mut text = String::new();
+        let mut chars = String::from_utf8(out).unwrap().chars().collect::<Vec<_>>();
+        chars.reverse();
+        while let Some(c) = chars.pop() {
+            if c == '\x1B' {
+                while !chars
+                    .pop()
+                    .is_some_and(|c| c.is_ascii_alphabetic() && c != '[')
+                {}
+            } else {
+                text.push(c);
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn draws_custom_border() {
+        let container = Container::new("ABCDEFGH".parse().unwrap(), (0, 0), (4, 3));
+        assert_eq!(drawn(&container), "ABBCHDGFFE");
+    }
+
+    #[test]
+    fn draws_title_and_separators() {
+        let mut container = Container::new(BorderType::Ascii, (0, 0), (12, 4));
+        container.set_separators(vec![0, 2, 3]);
+        container.set_title("Title", Align::End, Role::Title);
+        assert_eq!(
+            drawn(&container),
+            "+----------+||||+----------++----------+ Title "
+        );
+
+        container.set_title("Long title", Align::Start, Role::Title);
+        assert!(drawn(&container).ends_with(" Long … "));
+
+        container.set_title("", Align::Center, Role::Title);
+        assert_eq!(
+            drawn(&container),
+            "+----------+||||+----------++----------+"
+        );
+    }
+
+    #[test]
+    fn inner_area_excludes_border_and_padding() {
+        let mut container = Container::new(BorderType::Unicode, (1, 2), (20, 8));
+        assert_eq!(container.inner(), Rect::new((2, 3), (18, 6)));
+        container.set_padding(2, 1);
+        assert_eq!(container.inner(), Rect::new((4, 4), (14, 4)));
+        container.set_size((4, 2));
+        assert_eq!(container.inner().size(), (0, 0));
     }
 }
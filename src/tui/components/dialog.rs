@@ -0,0 +1,175 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use std::cmp::max;
+use termion::cursor;
+
+use crate::tui::components::label::wrap_text;
+use crate::tui::components::{BorderType, Container, Label};
+use crate::tui::layout::{Align, Constraint::*, Layout, Rect};
+
+use std::io::Write;
+
+use crate::tui::theme::Role;
+use crate::tui::{Draw, Theme};
+
+use crate::draw2tty;
+
+const MIN_WIDTH: u16 = 30;
+const BUTTON_YES: &str = "[ Yes ]";
+const BUTTON_NO: &str = "[ No ]";
+
+pub struct Dialog {
+    position: (u16, u16),
+    size: (u16, u16),
+    title: String,
+    question: String,
+    lines: Vec<Label>,
+    buttons: Vec<Rect>,
+    container: Container,
+    confirmed: bool,
+}
+
+impl Dialog {
+    pub fn new(title: &str, question: &str, border: BorderType) -> Self {
+        let mut container = Container::new(border, (0, 0), (0, 0));
+        container.set_title(title, Align::Center, Role::Title);
+        container.set_padding(1, 0);
+
+        let mut dialog = Self {
+            position: (0, 0),
+            size: (0, 0),
+            title: title.to_owned(),
+            question: question.to_owned(),
+            lines: Vec::new(),
+            buttons: Vec::new(),
+            container,
+            confirmed: false,
+        };
+        dialog.fit(u16::MAX);
+
+        dialog
+    }
+
+    /// Keep the dialog within `max_width` columns, wrapping the question.
+    pub fn fit(&mut self, max_width: u16) {
+        let title_len = self.title.chars().count();
+        let question_len = self
+            .question
+            .lines()
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        // Leave room for the border and the padding
+        let width = max(MIN_WIDTH, max(title_len + 2, question_len) as u16)
+            .min(max_width.saturating_sub(4));
+        let question_lines = wrap_text(&self.question, width as usize);
+        // The question, a separator and the buttons
+        let height = question_lines.len() as u16 + 2;
+
+        self.size = (width + 4, height + 2);
+        self.container.set_size(self.size);
+
+        let rows = Layout::rows(&[Min(1), Fixed(1), Fixed(1)]).split(self.container.inner());
+        self.container.set_separators(vec![rows[1].y]);
+        self.lines = question_lines
+            .iter()
+            .zip(rows[0].y..)
+            .map(|(line, y)| Label::new(line, (rows[0].x, y)))
+            .collect();
+        self.buttons = Layout::columns(&[
+            Fixed(BUTTON_YES.len() as u16),
+            Fixed(BUTTON_NO.len() as u16),
+        ])
+        .spacing(3)
+        .align(Align::Center)
+        .split(rows[2]);
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    pub fn set_position(&mut self, position: (u16, u16)) {
+        self.position = position;
+    }
+
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed
+    }
+
+    pub fn toggle(&mut self) {
+        self.confirmed = !self.confirmed;
+    }
+}
+
+impl<T: Write> Draw<T> for Dialog {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), focused: bool) {
+        let new_origin = (origin.0 + self.position.0, origin.1 + self.position.1);
+        let (x, y) = new_origin;
+        let (width, height) = self.size;
+
+        // Blank the area below the dialog first
+        for row in 1..height - 1 {
+            draw2tty!(
+                tty,
+                "{}{: <2$}",
+                cursor::Goto(x + 1, y + row),
+                "",
+                width as usize - 2
+            );
+        }
+
+        self.container.draw(tty, theme, new_origin, focused);
+        for line in self.lines.iter() {
+            line.draw(tty, theme, new_origin, false);
+        }
+
+        let buttons = [(BUTTON_YES, self.confirmed), (BUTTON_NO, !self.confirmed)];
+        for (&(text, selected), area) in buttons.iter().zip(self.buttons.iter()) {
+            let role = if selected {
+                Role::Selection
+            } else {
+                Role::Label
+            };
+            // Cut the buttons on tiny terminals instead of wrapping them
+            let text: String = text.chars().take(area.width as usize).collect();
+            draw2tty!(
+                tty,
+                "{}{}{}{}",
+                cursor::Goto(x + area.x, y + area.y),
+                theme.paint(role),
+                text,
+                theme.plain()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_narrow_terminals() {
+        let question = "Do you really want to shut down the system right now?";
+        let mut dialog = Dialog::new("Confirm shutdown", question, BorderType::Unicode);
+        assert_eq!(dialog.size(), (57, 5));
+
+        dialog.fit(40);
+        assert_eq!(dialog.size(), (40, 6));
+        assert_eq!(dialog.lines.len(), 2);
+
+        for width in 0..8 {
+            dialog.fit(width);
+            assert!(dialog.size().0 <= 4.max(width));
+            dialog.draw(&mut Vec::new(), &Theme::default(), (1, 1), true);
+        }
+    }
+}
@@ -8,31 +8,57 @@
 
 use nix::sys::utsname::uname;
 use std::fmt;
+use termion::cursor;
 
-use crate::tui::components::{BorderType, Container, Label, TextInput};
+use crate::config::FormConfig;
+use crate::tui::components::{BorderType, Completion, Container, Label, Selector, TextInput};
+use crate::tui::layout::{Align, Constraint::*, Layout, Margin, Rect};
 
 use std::io::Write;
 
-use crate::tui::{Draw, KeyboardInput};
+use crate::tui::theme::Role;
+use crate::tui::{Draw, KeyboardInput, Theme};
 
-const DEFAULT_PROMPT: &str = "Login:";
+use crate::draw2tty;
 
 #[derive(PartialEq)]
 pub enum FormElement {
     Prompt,
-    //Session,
+    Session,
 }
 
+/// Arrangement of the form depending on the available space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FormLayout {
+    /// Boxed form of the configured size.
+    Full,
+    /// Borderless form with a row for each element.
+    Compact,
+    /// A single row showing the focused element.
+    Line,
+}
+
+const COMPACT_MIN_WIDTH: u16 = 24;
+const COMPACT_HEIGHT: u16 = 4;
+
 pub struct LoginForm<T> {
     position: (u16, u16),
-    _size: (u16, u16),
+    size: (u16, u16),
+    full_size: (u16, u16),
+    layout: FormLayout,
     host_label: Label,
     session_label: Label,
+    session_selector: Selector,
     input_label: Label,
     prompt_label: Label,
+    user: Option<String>,
+    user_label: Option<Label>,
     prompt_input: TextInput<T>,
+    completion: Completion,
+    completing: bool,
     container: Container,
     focus: FormElement,
+    default_prompt: String,
     _phantom: std::marker::PhantomData<T>,
 }
 
@@ -43,46 +69,300 @@ impl<T> fmt::Display for LoginForm<T> {
 }
 
 impl<T: Write> LoginForm<T> {
-    pub fn new(position: (u16, u16), size: (u16, u16)) -> Self {
-        let uts = uname();
-        let host_len = uts.nodename().chars().count() as u16;
-        let host_label = Label::new(uts.nodename(), ((size.0 - host_len) / 2, 2));
-
-        let session_label = Label::new("Session:", (3, size.1 - 7));
-
-        let input_label = Label::new(">", (4, size.1 - 3));
-        let prompt_label = Label::new(DEFAULT_PROMPT, (3, size.1 - 5));
-        let prompt_input = TextInput::new(size.0 as usize - 10, false, (7, size.1 - 3));
-
-        let container = Container::new(BorderType::Unicode, (0, 0), size);
-
-        Self {
-            position,
-            _size: size,
-            host_label,
-            session_label,
-            input_label,
-            prompt_label,
-            prompt_input,
+    pub fn new(config: &FormConfig, border: BorderType) -> Self {
+        let size = (config.width, config.height);
+
+        let title = match config.title {
+            Some(ref title) => title.clone(),
+            None => uname().nodename().to_owned(),
+        };
+
+        let mut container = Container::new(border, (0, 0), size);
+        container.set_title(&title, config.title_align, Role::Title);
+        container.set_padding(2, 1);
+
+        let mut form = Self {
+            position: (0, 0),
+            size,
+            full_size: size,
+            layout: FormLayout::Full,
+            host_label: Label::new(&title, (0, 0)).with_role(Role::Title),
+            session_label: Label::new("Session:", (0, 0)),
+            session_selector: Selector::new(0, (0, 0)),
+            input_label: Label::new(">", (0, 0)),
+            prompt_label: Label::new(&config.prompt, (0, 0)),
+            user: None,
+            user_label: None,
+            prompt_input: TextInput::new(0, false, (0, 0)),
+            completion: Completion::new((0, 0), 0, 0, border),
+            completing: true,
             container,
             focus: FormElement::Prompt,
+            default_prompt: config.prompt.clone(),
             _phantom: std::marker::PhantomData,
+        };
+        form.arrange();
+
+        form
+    }
+
+    /// Leave out the title, e.g. if it is shown elsewhere.
+    pub fn hide_title(&mut self) {
+        self.container.set_title("", Align::Center, Role::Title);
+        self.host_label.set_text("");
+        self.arrange();
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    pub fn layout(&self) -> FormLayout {
+        self.layout
+    }
+
+    pub fn set_position(&mut self, position: (u16, u16)) {
+        self.position = position;
+    }
+
+    /// Use the largest layout fitting into `area`.
+    pub fn fit(&mut self, area: (u16, u16)) {
+        let (width, height) = area;
+        let (layout, size) = if width >= self.full_size.0 && height >= self.full_size.1 {
+            (FormLayout::Full, self.full_size)
+        } else if width >= COMPACT_MIN_WIDTH && height >= COMPACT_HEIGHT {
+            let width = width.min(self.full_size.0);
+            (FormLayout::Compact, (width, COMPACT_HEIGHT))
+        } else {
+            (FormLayout::Line, (width, 1))
+        };
+
+        self.layout = layout;
+        self.size = size;
+        self.arrange();
+    }
+
+    /// Limit the user list below the form to `rows` entries.
+    pub fn set_completion_rows(&mut self, rows: u16) {
+        self.completion.set_rows(rows);
+    }
+
+    /// Place all elements for the current layout and size.
+    fn arrange(&mut self) {
+        let area = Rect::new((0, 0), self.size);
+
+        match self.layout {
+            FormLayout::Full => {
+                // Stack the rows at the bottom, the session apart from the prompt
+                self.container.set_size(self.size);
+                let rows = Layout::rows(&[
+                    Fill(1),
+                    Fixed(1), // session
+                    Fixed(1), // separator
+                    Fixed(1), // prompt
+                    Fixed(1),
+                    Fixed(1), // input
+                ])
+                .split(self.container.inner());
+                self.container.set_separators(vec![rows[2].y]);
+
+                self.arrange_session(rows[1]);
+                self.arrange_prompt(rows[3], rows[5], 2);
+
+                let below = Rect::new((0, area.height), (area.width, 0));
+                self.arrange_completion(below.inset(Margin::symmetric(2, 0)));
+            }
+            FormLayout::Compact => {
+                let rows = Layout::rows(&[Fixed(1); COMPACT_HEIGHT as usize]).split(area);
+
+                self.host_label.set_length(area.width as usize);
+                let title_size = (self.host_label.width() as u16, 1);
+                self.host_label.set_position(
+                    rows[0]
+                        .align(title_size, Align::Center, Align::Start)
+                        .position(),
+                );
+
+                self.arrange_session(rows[1]);
+                self.arrange_prompt(rows[2], rows[3], 1);
+
+                self.arrange_completion(Rect::new((0, area.height), (area.width, 0)));
+            }
+            FormLayout::Line => {
+                self.arrange_session(area);
+
+                // Give most of the row to the input
+                let limit = Layout::columns(&[Percentage(33), Fill(1)]).split(area)[0];
+                self.prompt_label.set_length(limit.width.max(1) as usize);
+                let prompt_len = self.prompt_label.width() as u16;
+                let columns = Layout::columns(&[Fixed(prompt_len), Fill(1)])
+                    .spacing(1)
+                    .split(area);
+                self.prompt_label.set_position(columns[0].position());
+                self.prompt_input.set_position(columns[1].position());
+                self.prompt_input.set_length(columns[1].width as usize);
+            }
         }
+
+        self.arrange_user();
+    }
+
+    fn arrange_session(&mut self, row: Rect) {
+        let columns = Layout::columns(&[Fixed(8), Fill(1)]).split(row);
+        self.session_label.set_position(columns[0].position());
+        self.session_selector.set_position(columns[1].position());
+        self.session_selector.set_length(columns[1].width as usize);
+    }
+
+    /// Place the prompt above the input, `spacing` columns after the marker.
+    fn arrange_prompt(&mut self, prompt_row: Rect, input_row: Rect, spacing: u16) {
+        self.prompt_label.set_length(0);
+        self.prompt_label.set_position(prompt_row.position());
+
+        let indent = Margin {
+            left: spacing / 2,
+            ..Margin::default()
+        };
+        let columns = Layout::columns(&[Fixed(1), Fill(1)])
+            .margin(indent)
+            .spacing(spacing)
+            .split(input_row);
+        self.input_label.set_position(columns[0].position());
+        self.prompt_input.set_position(columns[1].position());
+        self.prompt_input.set_length(columns[1].width as usize);
+    }
+
+    /// The user list opens downwards from the top of `area`.
+    fn arrange_completion(&mut self, area: Rect) {
+        self.completion.set_position(area.position());
+        self.completion.set_width(area.width);
+    }
+
+    /// Show the user at the right end of the prompt row.
+    fn arrange_user(&mut self) {
+        let area = match self.layout {
+            FormLayout::Full => self.container.inner(),
+            FormLayout::Compact => Rect::new((0, 0), self.size),
+            FormLayout::Line => {
+                self.user_label = None;
+                return;
+            }
+        };
+        let (_, y) = self.prompt_label.position();
+        let row = Rect::new((area.x, y), (area.width, 1));
+
+        self.user_label = self.user.as_ref().map(|user| {
+            let text = format!("User: {}", user);
+            let max_len = row.width / 2;
+            let len = (text.chars().count() as u16).min(max_len);
+            let position = row.align((len, 1), Align::End, Align::Start).position();
+            Label::new_truncated(&text, position, max_len as usize)
+        });
     }
 
     pub fn reset(&mut self) {
-        self.prompt_label.set_text(DEFAULT_PROMPT);
+        self.prompt_label.set_text(&self.default_prompt);
         self.prompt_input.clear();
         self.focus = FormElement::Prompt;
+        self.completing = true;
+        self.refresh_completion();
+        self.arrange();
     }
 
     pub fn set_prompt(&mut self, prompt: &str) {
         self.prompt_label.set_text(prompt);
+        self.stop_completion();
+        self.arrange();
     }
 
     pub fn set_secret_prompt(&mut self, prompt: &str) {
         self.prompt_label.set_text(prompt);
         self.prompt_input.set_secret(true);
+        self.stop_completion();
+        self.arrange();
+    }
+
+    /// Set the `(username, real name)` pairs offered while asking for the username.
+    pub fn set_users(&mut self, users: Vec<(String, String)>) {
+        self.completion.set_candidates(users);
+        self.refresh_completion();
+    }
+
+    /// Complete the username as far as it is unambiguous.
+    pub fn complete(&mut self) {
+        if !self.completing || self.focus != FormElement::Prompt {
+            return;
+        }
+
+        let input = self.prompt_input.to_string();
+        let completed = match self.completion.selection() {
+            Some(name) => Some(name.to_owned()),
+            None => self.completion.common_prefix(&input),
+        };
+        if let Some(completed) = completed {
+            self.prompt_input.set_text(&completed);
+            self.refresh_completion();
+        }
+    }
+
+    /// Take over the user selected in the completion list, if any.
+    pub fn accept_completion(&mut self) {
+        if !self.completing {
+            return;
+        }
+        if let Some(name) = self.completion.selection().map(str::to_owned) {
+            self.prompt_input.set_text(&name);
+            self.refresh_completion();
+        }
+    }
+
+    fn stop_completion(&mut self) {
+        self.completing = false;
+        self.prompt_input.set_hint(None);
+    }
+
+    fn refresh_completion(&mut self) {
+        if !self.completing {
+            return;
+        }
+
+        let input = self.prompt_input.to_string();
+        self.completion.update(&input);
+        self.refresh_hint(&input);
+    }
+
+    fn refresh_hint(&mut self, input: &str) {
+        let hint = self
+            .completion
+            .suggestion(input)
+            .and_then(|name| name.strip_prefix(input))
+            .filter(|rest| !rest.is_empty())
+            .map(str::to_owned);
+        self.prompt_input.set_hint(hint);
+    }
+
+    fn is_completion_visible(&self) -> bool {
+        self.completing
+            && self.focus == FormElement::Prompt
+            && self.completion.has_matches()
+            && (!KeyboardInput::<T>::is_empty(self) || self.completion.is_selected())
+    }
+
+    pub fn set_user(&mut self, user: Option<&str>) {
+        self.user = user.map(str::to_owned);
+        self.arrange_user();
+    }
+
+    pub fn set_sessions(&mut self, names: Vec<String>, selected: usize) {
+        self.session_selector.set_items(names, selected);
+    }
+
+    pub fn select_session(&mut self, index: usize) {
+        self.session_selector.select(index);
+    }
+
+    pub fn session(&self) -> usize {
+        self.session_selector.selected()
     }
 
     pub fn focus(&self) -> &FormElement {
@@ -90,43 +370,52 @@ impl<T: Write> LoginForm<T> {
     }
 
     pub fn focus_prev(&mut self) {
-        //self.focus = match self.focus {
-        //    FormElement::Username => FormElement::Username,
-        //    FormElement::Password => FormElement::Username,
-        //};
+        if self.completion.is_selected() && self.is_completion_visible() {
+            self.completion.select_prev();
+            self.refresh_hint(&self.prompt_input.to_string());
+            return;
+        }
+
+        self.focus = match self.focus {
+            FormElement::Session => FormElement::Session,
+            FormElement::Prompt => FormElement::Session,
+        };
     }
 
     pub fn focus_next(&mut self) {
-        //self.focus = match self.focus {
-        //    FormElement::Username => FormElement::Password,
-        //    FormElement::Password => FormElement::Password,
-        //};
+        if self.completing && self.focus == FormElement::Prompt && self.completion.has_matches() {
+            self.completion.select_next();
+            self.refresh_hint(&self.prompt_input.to_string());
+            return;
+        }
+
+        self.focus = match self.focus {
+            FormElement::Session => FormElement::Prompt,
+            FormElement::Prompt => FormElement::Prompt,
+        };
     }
 }
 
 impl<T: Write> KeyboardInput<T> for LoginForm<T> {
     fn clear(&mut self) {
-        KeyboardInput::<T>::clear(match self.focus {
-            FormElement::Prompt => &mut self.prompt_input,
-        });
+        if let FormElement::Prompt = self.focus {
+            KeyboardInput::<T>::clear(&mut self.prompt_input);
+            self.refresh_completion();
+        }
     }
 
     fn push(&mut self, c: char) {
-        KeyboardInput::<T>::push(
-            match self.focus {
-                FormElement::Prompt => &mut self.prompt_input,
-            },
-            c,
-        );
+        if let FormElement::Prompt = self.focus {
+            KeyboardInput::<T>::push(&mut self.prompt_input, c);
+            self.refresh_completion();
+        }
     }
 
     fn pop(&mut self, right: bool) {
-        KeyboardInput::<T>::pop(
-            match self.focus {
-                FormElement::Prompt => &mut self.prompt_input,
-            },
-            right,
-        );
+        if let FormElement::Prompt = self.focus {
+            KeyboardInput::<T>::pop(&mut self.prompt_input, right);
+            self.refresh_completion();
+        }
     }
 
     fn is_empty(&self) -> bool {
@@ -138,46 +427,175 @@ impl<T: Write> KeyboardInput<T> for LoginForm<T> {
     }
 
     fn cursor_left(&mut self) {
-        KeyboardInput::<T>::cursor_left(match self.focus {
-            FormElement::Prompt => &mut self.prompt_input,
-        });
+        match self.focus {
+            FormElement::Prompt => KeyboardInput::<T>::cursor_left(&mut self.prompt_input),
+            FormElement::Session => self.session_selector.prev(),
+        }
     }
 
     fn cursor_right(&mut self) {
-        KeyboardInput::<T>::cursor_right(match self.focus {
-            FormElement::Prompt => &mut self.prompt_input,
-        });
+        match self.focus {
+            FormElement::Prompt => KeyboardInput::<T>::cursor_right(&mut self.prompt_input),
+            FormElement::Session => self.session_selector.next(),
+        }
     }
 
     fn cursor_start(&mut self) {
-        KeyboardInput::<T>::cursor_start(match self.focus {
-            FormElement::Prompt => &mut self.prompt_input,
-        });
+        if let FormElement::Prompt = self.focus {
+            KeyboardInput::<T>::cursor_start(&mut self.prompt_input);
+        }
     }
 
     fn cursor_end(&mut self) {
-        KeyboardInput::<T>::cursor_end(match self.focus {
-            FormElement::Prompt => &mut self.prompt_input,
-        });
+        if let FormElement::Prompt = self.focus {
+            KeyboardInput::<T>::cursor_end(&mut self.prompt_input);
+        }
     }
 }
 
 impl<T: Write> Draw<T> for LoginForm<T> {
-    fn draw(&self, tty: &mut T, origin: (u16, u16), focused: bool) {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), focused: bool) {
         let new_origin = (origin.0 + self.position.0, origin.1 + self.position.1);
 
-        self.container.draw(tty, new_origin, focused);
-        self.host_label.draw(tty, new_origin, false);
+        if self.layout == FormLayout::Line {
+            let (x, y) = new_origin;
+            draw2tty!(
+                tty,
+                "{}{: <2$}",
+                cursor::Goto(x, y),
+                "",
+                self.size.0 as usize
+            );
+            match self.focus {
+                FormElement::Session => {
+                    self.session_label.draw(tty, theme, new_origin, true);
+                    self.session_selector.draw(tty, theme, new_origin, true);
+                }
+                FormElement::Prompt => {
+                    self.prompt_label.draw(tty, theme, new_origin, true);
+                    self.prompt_input.draw(tty, theme, new_origin, true);
+                }
+            }
+            return;
+        }
+
+        if self.layout == FormLayout::Full {
+            self.container.draw(tty, theme, new_origin, focused);
+        }
+        if self.layout == FormLayout::Compact {
+            self.host_label.draw(tty, theme, new_origin, false);
+        }
 
-        self.session_label.draw(
-            tty, new_origin, false, /*self.focus == FormElement::Session*/
-        );
+        self.session_label
+            .draw(tty, theme, new_origin, self.focus == FormElement::Session);
+        self.session_selector
+            .draw(tty, theme, new_origin, self.focus == FormElement::Session);
 
         self.input_label
-            .draw(tty, new_origin, self.focus == FormElement::Prompt);
+            .draw(tty, theme, new_origin, self.focus == FormElement::Prompt);
         self.prompt_label
-            .draw(tty, new_origin, self.focus == FormElement::Prompt);
+            .draw(tty, theme, new_origin, self.focus == FormElement::Prompt);
+        if let Some(ref user_label) = self.user_label {
+            user_label.draw(tty, theme, new_origin, false);
+        }
+        self.completion
+            .draw(tty, theme, new_origin, self.is_completion_visible());
         self.prompt_input
-            .draw(tty, new_origin, self.focus == FormElement::Prompt);
+            .draw(tty, theme, new_origin, self.focus == FormElement::Prompt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form() -> LoginForm<Vec<u8>> {
+        let mut form = LoginForm::new(&FormConfig::default(), BorderType::Unicode);
+        form.set_sessions(vec!["shell".to_owned(), "desktop".to_owned()], 0);
+        form.set_user(Some("alice"));
+        form
+    }
+
+    #[test]
+    fn picks_largest_layout_that_fits() {
+        let mut form = form();
+        let cases = [
+            ((80, 22), FormLayout::Full, (64, 11)),
+            ((64, 11), FormLayout::Full, (64, 11)),
+            ((80, 8), FormLayout::Compact, (64, 4)),
+            ((40, 22), FormLayout::Compact, (40, 4)),
+            ((23, 22), FormLayout::Line, (23, 1)),
+            ((80, 3), FormLayout::Line, (80, 1)),
+        ];
+        for &(area, layout, size) in cases.iter() {
+            form.fit(area);
+            assert_eq!((form.layout(), form.size()), (layout, size), "{:?}", area);
+        }
+    }
+
+    #[test]
+    fn moves_focus_between_session_and_prompt() {
+        let mut form = form();
+        assert!(*form.focus() == FormElement::Prompt);
+        form.focus_next();
+        assert!(*form.focus() == FormElement::Prompt);
+
+        form.focus_prev();
+        assert!(*form.focus() == FormElement::Session);
+        form.focus_prev();
+        assert!(*form.focus() == FormElement::Session);
+
+        // Left and right pick the session, typing goes nowhere
+        form.cursor_right();
+        assert_eq!(form.session(), 1);
+        form.cursor_right();
+        assert_eq!(form.session(), 0);
+        form.push('a');
+        assert!(form.is_empty());
+
+        form.focus_next();
+        assert!(*form.focus() == FormElement::Prompt);
+        form.push('a');
+        form.cursor_left();
+        assert!(!form.is_empty());
+        assert_eq!(form.session(), 0);
+    }
+
+    #[test]
+    fn hides_the_title() {
+        let config = FormConfig {
+            title: Some("Welcome".to_owned()),
+            ..FormConfig::default()
+        };
+        let mut form: LoginForm<Vec<u8>> = LoginForm::new(&config, BorderType::Ascii);
+        let drawn = |form: &LoginForm<Vec<u8>>| {
+            let mut out = Vec::new();
+            form.draw(&mut out, &Theme::default(), (1, 1), true);
+            String::from_utf8(out).unwrap()
+        };
+
+        for &area in [(80, 22), (40, 8)].iter() {
+            form.fit(area);
+            assert!(drawn(&form).contains("Welcome"), "{:?}", area);
+        }
+        form.hide_title();
+        for &area in [(80, 22), (40, 8)].iter() {
+            form.fit(area);
+            assert!(!drawn(&form).contains("Welcome"), "{:?}", area);
+        }
+    }
+
+    #[test]
+    fn draws_on_tiny_terminals() {
+        let mut form = form();
+        for &area in [(0, 0), (1, 1), (5, 1), (24, 4), (30, 2)].iter() {
+            form.fit(area);
+            form.set_completion_rows(area.1);
+            "alice-with-a-long-name".chars().for_each(|c| form.push(c));
+            form.draw(&mut Vec::new(), &Theme::default(), (1, 1), true);
+            form.focus_prev();
+            form.draw(&mut Vec::new(), &Theme::default(), (1, 1), true);
+            form.reset();
+        }
     }
 }
@@ -7,16 +7,14 @@
 //
 
 use std::cmp::{max, min};
-use std::iter;
-use termion::color;
 use termion::cursor;
-use termion::style;
 
 use std::fmt;
 use std::io::Write;
 use std::iter::FromIterator;
 
-use crate::tui::{Draw, KeyboardInput};
+use crate::tui::theme::Role;
+use crate::tui::{Draw, KeyboardInput, Theme};
 
 use crate::draw2tty;
 
@@ -27,6 +25,7 @@ pub struct TextInput<T> {
     cursor: usize,
     masked: bool,
     is_secret: bool,
+    hint: Option<String>,
     position: (u16, u16),
     _phantom: std::marker::PhantomData<T>,
 }
@@ -40,16 +39,39 @@ impl<T> TextInput<T> {
             cursor: 0,
             masked: is_secret,
             is_secret,
+            hint: None,
             position,
             _phantom: std::marker::PhantomData,
         }
     }
 
+    pub fn set_position(&mut self, position: (u16, u16)) {
+        self.position = position;
+    }
+
+    /// Show at most `length` characters, scrolling to keep the cursor visible.
+    pub fn set_length(&mut self, length: usize) {
+        self.vis_len = max(length, 1);
+        self.vis_start = 0;
+        self.refresh_visible();
+    }
+
     pub fn set_secret(&mut self, is_secret: bool) {
         self.is_secret = is_secret;
         self.masked = is_secret;
     }
 
+    /// Replace the content and move the cursor to its end.
+    pub fn set_text(&mut self, text: &str) {
+        self.data = text.chars().collect();
+        self.set_cursor(self.data.len());
+    }
+
+    /// Set text shown greyed out after the cursor, if it is at the end.
+    pub fn set_hint(&mut self, hint: Option<String>) {
+        self.hint = hint;
+    }
+
     fn toggle(&mut self) {
         if self.is_secret {
             self.masked = !self.masked;
@@ -146,36 +168,56 @@ impl<T: Write> KeyboardInput<T> for TextInput<T> {
 }
 
 impl<T: Write> Draw<T> for TextInput<T> {
-    fn draw(&self, tty: &mut T, origin: (u16, u16), focused: bool) {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), focused: bool) {
         let length = min(self.data.len() - self.vis_start, self.vis_len);
         let (x, y) = (origin.0 + self.position.0, origin.1 + self.position.1);
 
-        let text = if self.masked {
-            String::from_iter(iter::repeat('*').take(length))
+        let (text, role) = if self.masked {
+            ("*".repeat(length), Role::InputMasked)
         } else {
-            String::from_iter(self.data.iter().skip(self.vis_start).take(length))
+            let text = String::from_iter(self.data.iter().skip(self.vis_start).take(length));
+            let role = if focused {
+                Role::InputFocused
+            } else {
+                Role::Input
+            };
+            (text, role)
         };
 
-        if focused {
-            draw2tty!(tty, "{}", style::Bold);
-        }
         draw2tty!(
             tty,
-            "{}{}{3: <2$}",
+            "{}{}{}{}{5: <4$}",
             cursor::Goto(x, y),
+            theme.paint(role),
             text,
+            theme.plain(),
             self.vis_len - length,
             ""
         );
 
+        if let Some(ref hint) = self.hint {
+            let free = self.vis_len - length;
+            if !self.masked && self.cursor == self.data.len() && free > 1 {
+                let hint: String = hint.chars().take(free - 1).collect();
+                draw2tty!(
+                    tty,
+                    "{}{}{}{}",
+                    cursor::Goto(x + length as u16, y),
+                    theme.paint(Role::Hint),
+                    hint,
+                    theme.plain()
+                );
+            }
+        }
+
         if self.vis_start > 0 {
             draw2tty!(
                 tty,
                 "{}{}{}{}",
                 cursor::Goto(x, y),
-                color::Fg(color::LightBlack),
+                theme.paint(Role::Hint),
                 "<",
-                color::Fg(color::Reset)
+                theme.plain()
             );
         }
         if self.data.len() > self.vis_start + self.vis_len {
@@ -183,21 +225,15 @@ impl<T: Write> Draw<T> for TextInput<T> {
                 tty,
                 "{}{}{}{}",
                 cursor::Goto(x + self.vis_len as u16 - 1, y),
-                color::Fg(color::LightBlack),
+                theme.paint(Role::Hint),
                 ">",
-                color::Fg(color::Reset)
+                theme.plain()
             );
         }
 
         if focused {
             let cursor = x as usize + self.cursor - self.vis_start;
-            draw2tty!(
-                tty,
-                "{}{}{}",
-                style::Reset,
-                cursor::Goto(cursor as u16, y),
-                cursor::Save
-            );
+            draw2tty!(tty, "{}{}", cursor::Goto(cursor as u16, y), cursor::Save);
         }
     }
 }
@@ -7,15 +7,15 @@
 //
 
 use termion::cursor;
-use termion::style;
 
 use std::io::Write;
 
-use crate::tui::Draw;
+use crate::tui::theme::Role;
+use crate::tui::{Draw, Theme};
 
 use crate::draw2tty;
 
-fn truncate_text(text: &str, length: Option<usize>) -> String {
+pub(super) fn truncate_text(text: &str, length: Option<usize>) -> String {
     match length {
         None | Some(0) => text.to_owned(),
         Some(len) => {
@@ -30,10 +30,47 @@ fn truncate_text(text: &str, length: Option<usize>) -> String {
     }
 }
 
+/// Break `text` into lines of at most `width` characters.
+///
+/// Lines are broken between words where possible, words longer than `width`
+/// are split. Empty lines are kept.
+pub(super) fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+
+    for line in text.lines() {
+        let mut current = String::new();
+        let mut current_len = 0;
+        for word in line.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            if current_len > 0 && current_len + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            while current_len + word.len() > width {
+                // Only a word not fitting into an empty line gets here
+                let rest = word.split_off(width);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            if current_len > 0 {
+                current.push(' ');
+                current_len += 1;
+            }
+            current_len += word.len();
+            current.extend(word);
+        }
+        lines.push(current);
+    }
+
+    lines
+}
+
 pub struct Label {
     text: String,
     position: (u16, u16),
     length: Option<usize>,
+    role: Option<Role>,
 }
 
 impl Label {
@@ -48,32 +85,82 @@ impl Label {
         };
 
         Self {
-            text: truncate_text(text, length),
+            text: text.to_owned(),
             position,
             length,
+            role: None,
         }
     }
 
+    /// Draw in the style of `role` instead of as a (focused) label.
+    pub fn with_role(mut self, role: Role) -> Self {
+        self.role = Some(role);
+        self
+    }
+
     pub fn set_text(&mut self, text: &str) {
-        self.text = truncate_text(text, self.length);
+        self.text = text.to_owned();
+    }
+
+    pub fn position(&self) -> (u16, u16) {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: (u16, u16)) {
+        self.position = position;
+    }
+
+    /// Truncate the text to `length` characters, 0 to show it in full.
+    pub fn set_length(&mut self, length: usize) {
+        self.length = match length {
+            0 => None,
+            _ => Some(length),
+        };
+    }
+
+    /// Number of characters drawn.
+    pub fn width(&self) -> usize {
+        let len = self.text.chars().count();
+        self.length.map_or(len, |max| len.min(max))
     }
 }
 
 impl<T: Write> Draw<T> for Label {
-    fn draw(&self, tty: &mut T, origin: (u16, u16), focused: bool) {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), focused: bool) {
         let (x, y) = (origin.0 + self.position.0, origin.1 + self.position.1);
+        let text = truncate_text(&self.text, self.length);
+        let role = match self.role {
+            Some(role) => role,
+            None if focused => Role::LabelFocused,
+            None => Role::Label,
+        };
 
-        if focused {
-            draw2tty!(
-                tty,
-                "{}{}{}{}",
-                cursor::Goto(x, y),
-                style::Bold,
-                self.text,
-                style::Reset
-            );
-        } else {
-            draw2tty!(tty, "{}{}", cursor::Goto(x, y), self.text);
-        }
+        draw2tty!(
+            tty,
+            "{}{}{}{}",
+            cursor::Goto(x, y),
+            theme.paint(role),
+            text,
+            theme.plain()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_text_between_words() {
+        assert_eq!(
+            wrap_text("Do you really want\nto  shut down?\n\nYes", 10),
+            vec!["Do you", "really", "want", "to shut", "down?", "", "Yes"]
+        );
+        assert_eq!(
+            wrap_text("at /nix/store/abcdefghij-x end", 8),
+            vec!["at", "/nix/sto", "re/abcde", "fghij-x", "end"]
+        );
+        assert_eq!(wrap_text("abc", 0), vec!["a", "b", "c"]);
+        assert!(wrap_text("", 10).is_empty());
     }
 }
@@ -6,97 +6,124 @@
 // that was distributed with this source code.
 //
 
-use std::cmp::{max, min};
+use std::cmp::max;
+use std::iter;
 
+use crate::tui::components::label::wrap_text;
 use crate::tui::components::{BorderType, Container, Label};
+use crate::tui::layout::{Align, Constraint, Layout};
 
 use std::io::Write;
 
-use crate::tui::Draw;
+use crate::tui::theme::Role;
+use crate::tui::{Draw, Theme};
 
 const MIN_WIDTH: u16 = 40;
 const MAX_WIDTH: u16 = 80;
 
-fn split_line(line: &str) -> (&str, Option<&str>) {
-    if line.chars().count() > MAX_WIDTH as usize {
-        let mut last_word = 0;
-
-        for (idx, (byte, chr)) in line.char_indices().enumerate() {
-            if chr.is_ascii_whitespace() {
-                last_word = byte;
-            }
-            if idx > MAX_WIDTH as usize {
-                let (l, rest) = line.split_at(last_word);
-                return (l.trim_end(), Some(rest.trim_start()));
-            }
-        }
-    }
-
-    (line, None)
-}
-
 pub struct Message {
     position: (u16, u16),
     size: (u16, u16),
-    label: Label,
     lines: Vec<Label>,
     container: Container,
+    role: Role,
+    summary: String,
 }
 
 impl Message {
-    pub fn new(title: &str, message: &str, position: (u16, u16)) -> Self {
+    /// A message box with a title in the style of `role`.
+    pub fn new(
+        title: &str,
+        message: &str,
+        role: Role,
+        border: BorderType,
+        position: (u16, u16),
+    ) -> Self {
         let message_lines: Vec<_> = message.lines().collect();
+        let texts = wrap_text(message, MAX_WIDTH as usize);
 
         let title_len = title.chars().count();
-        let message_len = message_lines
-            .iter()
-            .map(|l| l.chars().count())
-            .max()
-            .expect("Message has no lines");
-
-        let label = Label::new_truncated(title, (2, 1), MAX_WIDTH as usize);
-
-        let mut lines = Vec::with_capacity(message_lines.len());
-        for line in message_lines.iter() {
-            let mut split = ("", Some(*line));
-            while let Some(rest) = split.1 {
-                split = split_line(rest);
-                let message = Label::new(split.0, (2, lines.len() as u16 + 2));
-                lines.push(message);
-            }
-        }
+        let message_len = texts.iter().map(|l| l.chars().count()).max().unwrap_or(0);
 
-        let width = min(
-            max(MIN_WIDTH, max(title_len, message_len) as u16),
-            MAX_WIDTH,
-        );
-        let height = lines.len() as u16 + 1;
+        let width = (max(title_len + 2, message_len) as u16).clamp(MIN_WIDTH, MAX_WIDTH);
+        let height = texts.len() as u16;
 
         let size = (width + 4, height + 2);
-        let container = Container::new(BorderType::Unicode, (0, 0), size);
+        let mut container = Container::new(border, (0, 0), size);
+        container.set_title(title, Align::Start, role);
+        container.set_padding(1, 0);
+
+        let rows = Layout::rows(&vec![Constraint::Fixed(1); texts.len()]).split(container.inner());
+        let lines = texts
+            .iter()
+            .zip(rows)
+            .map(|(text, row)| Label::new(text, row.position()))
+            .collect();
+
+        let summary = iter::once(title)
+            .chain(message_lines)
+            .collect::<Vec<_>>()
+            .join(" ");
 
         Self {
             position,
             size,
-            label,
             lines,
             container,
+            role,
+            summary,
         }
     }
 
-    pub fn height(&self) -> u16 {
-        self.size.1
+    pub fn set_position(&mut self, position: (u16, u16)) {
+        self.position = position;
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Title and text on a single line.
+    pub fn summary(&self) -> &str {
+        &self.summary
     }
 }
 
 impl<T: Write> Draw<T> for Message {
-    fn draw(&self, tty: &mut T, origin: (u16, u16), focused: bool) {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), focused: bool) {
         let new_origin = (origin.0 + self.position.0, origin.1 + self.position.1);
 
-        self.container.draw(tty, new_origin, focused);
-        self.label.draw(tty, new_origin, true);
+        self.container.draw(tty, theme, new_origin, focused);
         for message in self.lines.iter() {
-            message.draw(tty, new_origin, false);
+            message.draw(tty, theme, new_origin, false);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str) -> Message {
+        Message::new("Error", text, Role::Error, BorderType::Ascii, (0, 0))
+    }
+
+    #[test]
+    fn splits_words_longer_than_the_box() {
+        let text = format!("Reason: {}", "x".repeat(120));
+        let message = message(&text);
+        assert_eq!(message.size(), (MAX_WIDTH + 4, 3 + 2));
+        assert_eq!(message.summary(), format!("Error {}", text));
+    }
+
+    #[test]
+    fn accepts_empty_text() {
+        let message = message("");
+        assert_eq!(message.size(), (MIN_WIDTH + 4, 2));
+        assert_eq!(message.summary(), "Error");
+    }
+}
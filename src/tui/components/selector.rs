@@ -0,0 +1,157 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use termion::cursor;
+
+use std::io::Write;
+
+use crate::tui::components::label::truncate_text;
+use crate::tui::theme::Role;
+use crate::tui::{Draw, Theme};
+
+use crate::draw2tty;
+
+pub struct Selector {
+    items: Vec<String>,
+    selected: usize,
+    position: (u16, u16),
+    length: usize,
+}
+
+impl Selector {
+    pub fn new(length: usize, position: (u16, u16)) -> Self {
+        Self {
+            items: Vec::new(),
+            selected: 0,
+            position,
+            length,
+        }
+    }
+
+    pub fn set_position(&mut self, position: (u16, u16)) {
+        self.position = position;
+    }
+
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+    }
+
+    pub fn set_items(&mut self, items: Vec<String>, selected: usize) {
+        self.items = items;
+        self.select(selected);
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn prev(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+}
+
+impl<T: Write> Draw<T> for Selector {
+    fn draw(&self, tty: &mut T, theme: &Theme, origin: (u16, u16), focused: bool) {
+        let (x, y) = (origin.0 + self.position.0, origin.1 + self.position.1);
+
+        // Leave room for the arrows on both sides
+        let width = self.length.saturating_sub(4);
+        let text = match self.items.get(self.selected) {
+            Some(item) => truncate_text(item, Some(width)),
+            None => String::new(),
+        };
+
+        let role = if focused {
+            Role::InputFocused
+        } else {
+            Role::Input
+        };
+        draw2tty!(
+            tty,
+            "{}{}  {: <4$}  {}",
+            cursor::Goto(x, y),
+            theme.paint(role),
+            text,
+            theme.plain(),
+            width
+        );
+
+        if focused {
+            draw2tty!(tty, "{}{}", cursor::Goto(x + 2, y), cursor::Save);
+        }
+        if focused && self.items.len() > 1 {
+            draw2tty!(
+                tty,
+                "{}{}<{}>{}",
+                cursor::Goto(x, y),
+                theme.paint(Role::Hint),
+                cursor::Goto(x + (self.length as u16).saturating_sub(1), y),
+                theme.plain()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selector(items: &[&str], selected: usize) -> Selector {
+        let mut selector = Selector::new(20, (0, 0));
+        selector.set_items(
+            items.iter().map(|&item| item.to_owned()).collect(),
+            selected,
+        );
+        selector
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut selector = selector(&["shell", "sway", "xfce"], 0);
+        selector.prev();
+        assert_eq!(selector.selected(), 2);
+        selector.next();
+        assert_eq!(selector.selected(), 0);
+        selector.next();
+        selector.next();
+        assert_eq!(selector.selected(), 2);
+    }
+
+    #[test]
+    fn clamps_the_selection() {
+        let mut empty = selector(&[], 3);
+        assert_eq!(empty.selected(), 0);
+        empty.prev();
+        empty.next();
+        assert_eq!(empty.selected(), 0);
+        empty.draw(&mut Vec::new(), &Theme::default(), (1, 1), true);
+
+        let mut single = selector(&["shell"], 3);
+        assert_eq!(single.selected(), 0);
+        single.prev();
+        assert_eq!(single.selected(), 0);
+        single.next();
+        assert_eq!(single.selected(), 0);
+
+        let mut pair = selector(&["shell", "sway"], 1);
+        pair.select(5);
+        assert_eq!(pair.selected(), 1);
+    }
+}
@@ -0,0 +1,337 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+use nix::libc;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::unistd;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+use termion::event::{parse_event, Key};
+
+use crate::tui::terminal;
+use crate::tui::LoginError;
+
+const STDIN: RawFd = 0;
+/// Milliseconds to wait for the rest of an escape sequence.
+const ESC_TIMEOUT: libc::c_int = 30;
+const SIGNALS: [Signal; 5] = [
+    Signal::SIGWINCH,
+    Signal::SIGTERM,
+    Signal::SIGHUP,
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+];
+
+/// Write end of the pipe the signal handler reports to.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+/// Set once asked to terminate.
+static TERMINATING: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(signum: libc::c_int) {
+    // Quit right away if the first request was not handled, e.g. while
+    // blocked waiting for greetd
+    if signum != libc::SIGWINCH && TERMINATING.swap(true, Ordering::SeqCst) {
+        terminal::restore();
+        unsafe { libc::_exit(128 + signum) };
+    }
+
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        let _ = unistd::write(fd, &[signum as u8]);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Timer {
+    /// Remove the shown messages.
+    ExpireMessages,
+    /// Update the clock.
+    Clock,
+    /// Try to reach greetd again.
+    Reconnect,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Key(Key),
+    Timer(Timer),
+    Resize,
+    Terminate,
+    /// greetd closed the connection or sent something unasked.
+    Disconnected,
+}
+
+/// Pending timers ordered by their deadline.
+#[derive(Default)]
+struct Timers {
+    deadlines: Vec<(Instant, Timer)>,
+}
+
+impl Timers {
+    fn schedule(&mut self, timer: Timer, after: Duration) {
+        self.cancel(timer);
+        let deadline = Instant::now() + after;
+        let idx = self
+            .deadlines
+            .iter()
+            .position(|(d, _)| *d > deadline)
+            .unwrap_or(self.deadlines.len());
+        self.deadlines.insert(idx, (deadline, timer));
+    }
+
+    fn cancel(&mut self, timer: Timer) {
+        self.deadlines.retain(|(_, t)| *t != timer);
+    }
+
+    /// Milliseconds until the next deadline, -1 without timers.
+    fn poll_timeout(&self) -> libc::c_int {
+        match self.deadlines.first() {
+            Some((deadline, _)) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                // Round up, polling 0ms until the deadline would busy loop
+                left.as_millis().saturating_add(1).min(i32::MAX as u128) as libc::c_int
+            }
+            None => -1,
+        }
+    }
+
+    fn expire(&mut self, now: Instant) -> impl Iterator<Item = Timer> + '_ {
+        let expired = self.deadlines.iter().take_while(|(d, _)| *d <= now).count();
+        self.deadlines.drain(..expired).map(|(_, timer)| timer)
+    }
+}
+
+/// Waits for keys, timers, signals and the greetd socket at once.
+pub struct EventLoop {
+    signals: RawFd,
+    timers: Timers,
+    pending: VecDeque<Event>,
+}
+
+impl EventLoop {
+    pub fn new() -> Result<Self, LoginError> {
+        let (read_end, write_end) = unistd::pipe2(OFlag::O_CLOEXEC | OFlag::O_NONBLOCK)?;
+        SIGNAL_PIPE.store(write_end, Ordering::Relaxed);
+
+        let action = SigAction::new(
+            SigHandler::Handler(on_signal),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        for &signal in SIGNALS.iter() {
+            // The handler only writes to the pipe, which is async-signal-safe
+            unsafe { sigaction(signal, &action) }?;
+        }
+
+        Ok(Self {
+            signals: read_end,
+            timers: Timers::default(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Fire `timer` once `after` has passed, replacing an earlier schedule.
+    pub fn schedule(&mut self, timer: Timer, after: Duration) {
+        self.timers.schedule(timer, after);
+    }
+
+    pub fn cancel(&mut self, timer: Timer) {
+        self.timers.cancel(timer);
+    }
+
+    /// Wait for the next event, watching `socket` for greetd hanging up.
+    ///
+    /// A negative `socket` is not watched.
+    pub fn wait(&mut self, socket: RawFd) -> Result<Event, LoginError> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            let mut fds = [
+                PollFd::new(STDIN, PollFlags::POLLIN),
+                PollFd::new(self.signals, PollFlags::POLLIN),
+                PollFd::new(socket, PollFlags::POLLIN),
+            ];
+            match poll(&mut fds, self.timers.poll_timeout()) {
+                Err(nix::Error::Sys(Errno::EINTR)) => continue,
+                Err(err) => return Err(err.into()),
+                Ok(_) => {}
+            }
+            let ready = |fd: &PollFd| fd.revents().is_some_and(|r| !r.is_empty());
+
+            let timers = self.timers.expire(Instant::now()).map(Event::Timer);
+            self.pending.extend(timers);
+            if ready(&fds[1]) {
+                self.read_signals()?;
+            }
+            if ready(&fds[0]) {
+                self.read_keys()?;
+            }
+            if ready(&fds[2]) {
+                self.pending.push_back(Event::Disconnected);
+            }
+        }
+    }
+
+    fn read_signals(&mut self) -> Result<(), LoginError> {
+        let mut buf = [0; 16];
+        loop {
+            let len = match unistd::read(self.signals, &mut buf) {
+                Err(nix::Error::Sys(Errno::EAGAIN)) => return Ok(()),
+                result => result?,
+            };
+            for &signum in buf[..len].iter() {
+                let event = match Signal::try_from(libc::c_int::from(signum)) {
+                    Ok(Signal::SIGWINCH) => Event::Resize,
+                    Ok(_) => Event::Terminate,
+                    Err(_) => continue,
+                };
+                self.pending.push_back(event);
+            }
+            if len < buf.len() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn read_keys(&mut self) -> Result<(), LoginError> {
+        let mut buf = [0; 1024];
+        let mut len = unistd::read(STDIN, &mut buf)?;
+        if len == 0 {
+            // The terminal went away
+            self.pending.push_back(Event::Terminate);
+            return Ok(());
+        }
+
+        // An escape sequence may be split across reads, give the rest a
+        // moment to arrive before taking a trailing escape byte as Esc
+        while buf[len - 1] == b'\x1B' && len < buf.len() && stdin_ready(ESC_TIMEOUT)? {
+            match unistd::read(STDIN, &mut buf[len..])? {
+                0 => break,
+                read => len += read,
+            }
+        }
+
+        let mut bytes = buf[..len]
+            .iter()
+            .map(|&b| Ok::<u8, io::Error>(b))
+            .peekable();
+        while let Some(Ok(byte)) = bytes.next() {
+            // A lone escape byte is the Esc key, not the start of a sequence
+            if byte == b'\x1B' && bytes.peek().is_none() {
+                self.pending.push_back(Event::Key(Key::Esc));
+            } else if let Ok(termion::event::Event::Key(key)) = parse_event(byte, &mut bytes) {
+                self.pending.push_back(Event::Key(key));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Wait up to `timeout` milliseconds for input on stdin.
+fn stdin_ready(timeout: libc::c_int) -> Result<bool, LoginError> {
+    loop {
+        let mut fds = [PollFd::new(STDIN, PollFlags::POLLIN)];
+        match poll(&mut fds, timeout) {
+            Err(nix::Error::Sys(Errno::EINTR)) => continue,
+            Err(err) => return Err(err.into()),
+            Ok(ready) => return Ok(ready > 0),
+        }
+    }
+}
+
+impl Drop for EventLoop {
+    fn drop(&mut self) {
+        let action = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty());
+        for &signal in SIGNALS.iter() {
+            let _ = unsafe { sigaction(signal, &action) };
+        }
+
+        let write_end = SIGNAL_PIPE.swap(-1, Ordering::Relaxed);
+        let _ = unistd::close(write_end);
+        let _ = unistd::close(self.signals);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expire_all(timers: &mut Timers) -> Vec<Timer> {
+        timers
+            .expire(Instant::now() + Duration::from_secs(3600))
+            .collect()
+    }
+
+    #[test]
+    fn fires_timers_by_deadline() {
+        let mut timers = Timers::default();
+        timers.schedule(Timer::Clock, Duration::from_secs(20));
+        timers.schedule(Timer::ExpireMessages, Duration::from_secs(10));
+        timers.schedule(Timer::Reconnect, Duration::from_secs(30));
+
+        let now = Instant::now();
+        let expired: Vec<_> = timers.expire(now + Duration::from_secs(25)).collect();
+        assert_eq!(expired, [Timer::ExpireMessages, Timer::Clock]);
+        assert_eq!(expire_all(&mut timers), [Timer::Reconnect]);
+        assert!(expire_all(&mut timers).is_empty());
+    }
+
+    #[test]
+    fn rescheduling_replaces_the_deadline() {
+        let mut timers = Timers::default();
+        timers.schedule(Timer::Clock, Duration::from_secs(10));
+        timers.schedule(Timer::ExpireMessages, Duration::from_secs(20));
+        timers.schedule(Timer::Clock, Duration::from_secs(30));
+
+        assert_eq!(timers.deadlines.len(), 2);
+        assert_eq!(
+            expire_all(&mut timers),
+            [Timer::ExpireMessages, Timer::Clock]
+        );
+    }
+
+    #[test]
+    fn cancels_timers() {
+        let mut timers = Timers::default();
+        timers.schedule(Timer::Clock, Duration::from_secs(10));
+        timers.schedule(Timer::Reconnect, Duration::from_secs(20));
+        timers.cancel(Timer::Clock);
+        timers.cancel(Timer::ExpireMessages);
+
+        assert_eq!(expire_all(&mut timers), [Timer::Reconnect]);
+    }
+
+    #[test]
+    fn polls_until_the_next_deadline() {
+        let mut timers = Timers::default();
+        assert_eq!(timers.poll_timeout(), -1);
+
+        timers.schedule(Timer::Clock, Duration::from_secs(10));
+        let timeout = timers.poll_timeout();
+        assert!(timeout > 9_000 && timeout <= 10_001, "{}", timeout);
+
+        // Overdue timers still wait a millisecond instead of spinning
+        timers.schedule(Timer::Reconnect, Duration::from_secs(0));
+        assert_eq!(timers.poll_timeout(), 1);
+        assert_eq!(
+            timers.expire(Instant::now()).collect::<Vec<_>>(),
+            [Timer::Reconnect]
+        );
+        assert!(timers.poll_timeout() > 1);
+    }
+}
@@ -6,144 +6,306 @@
 // that was distributed with this source code.
 //
 
+use nix::sys::utsname::uname;
 use std::io;
 use termion::clear;
-use termion::color;
 use termion::cursor;
 use termion::event::Key;
 use termion::raw;
 
-use crate::tui::components::{FormElement, LoginForm};
+use crate::config::{BorderConfig, Config, FormPlacement, ScreenConfig, ScreenRow};
+use crate::power::PowerAction;
+use crate::session::Session;
+use crate::tui::components::{FormElement, FormLayout, LoginForm};
 use crate::tui::{LoginAction, LoginError};
+use crate::users::User;
 
-use crate::tui::components::Message;
+use crate::tui::components::{Clock, Dialog, Label, Message};
 
 use std::io::Write;
-use termion::input::TermRead;
+use std::os::unix::io::RawFd;
+use std::time::Duration;
 use termion::raw::IntoRawMode;
 
-use crate::tui::{Draw, GreetUI, KeyboardInput};
+use crate::tui::event::{Event, EventLoop, Timer};
+use crate::tui::keymap::key_name;
+use crate::tui::layout::{Align, Constraint, Layout, Margin, Rect};
+use crate::tui::terminal;
+use crate::tui::theme::Role;
+use crate::tui::{Action, Draw, GreetUI, KeyboardInput, Keymap, LoginUI, Theme};
 
 use crate::draw2tty;
 
+const MAX_COMPLETION_ROWS: u16 = 5;
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Position of a box of `size` in the middle of `area`.
+fn center(area: (u16, u16), size: (u16, u16)) -> (u16, u16) {
+    Rect::new((0, 0), area)
+        .align(size, Align::Center, Align::Center)
+        .position()
+}
+
+const POWER_ACTIONS: [(Action, PowerAction); 4] = [
+    (Action::PowerOff, PowerAction::PowerOff),
+    (Action::Reboot, PowerAction::Reboot),
+    (Action::Suspend, PowerAction::Suspend),
+    (Action::Hibernate, PowerAction::Hibernate),
+];
+
 pub struct TerminalUI<T: Write> {
     tty: cursor::HideCursor<T>,
+    size: (u16, u16),
+    screen: ScreenConfig,
+    rows: Vec<(ScreenRow, Rect)>,
+    body: Rect,
+    banner: Label,
+    clock: Clock,
     login_form: LoginForm<T>,
     messages: Vec<Message>,
+    keymap: Keymap,
+    power_actions: Vec<(Action, PowerAction)>,
+    confirm: Option<(PowerAction, Dialog)>,
+    status: Option<String>,
+    theme: Theme,
+    borders: BorderConfig,
+    events: EventLoop,
 }
 
 impl TerminalUI<raw::RawTerminal<io::Stdout>> {
-    pub fn init() -> Result<Self, LoginError> {
+    pub fn init(config: &Config) -> Result<Self, LoginError> {
         let stdout = io::stdout();
         if !termion::is_tty(&stdout) {
             return Err(LoginError::NotATTY);
         }
 
-        let tty = stdout.into_raw_mode().map(cursor::HideCursor::from)?;
+        terminal::save()?;
+        let tty = stdout.into_raw_mode()?;
 
-        let (width, height) = termion::terminal_size().unwrap();
-        let login_form = LoginForm::new(((width - 64) / 2, (height - 11) / 2), (64, 11));
+        let mut ui = Self::new(tty, config, terminal::is_utf8_locale())?;
+        ui.layout();
+        ui.clear();
+        ui.redraw();
+        if ui.screen.rows.contains(&ScreenRow::Clock) {
+            ui.events.schedule(Timer::Clock, ui.clock.next_tick());
+        }
+
+        Ok(ui)
+    }
+}
+
+impl<T: Write> TerminalUI<T> {
+    /// A greeter drawing to `tty`, `utf8` tells if borders may use UTF-8.
+    fn new(tty: T, config: &Config, utf8: bool) -> Result<Self, LoginError> {
+        let borders = config.borders.fallback(utf8);
+        let mut login_form = LoginForm::new(&config.form, borders.form);
+        // The banner row already shows the title
+        if config.screen.rows.contains(&ScreenRow::Banner) {
+            login_form.hide_title();
+        }
 
         let messages = Vec::with_capacity(3);
 
-        let mut ui = Self {
-            tty,
+        let power_actions = POWER_ACTIONS
+            .iter()
+            .filter(|(_, action)| config.power.is_enabled(*action))
+            .cloned()
+            .collect();
+
+        let mut theme = config.theme.clone();
+        theme.detect_colors();
+
+        let banner = match config.form.title {
+            Some(ref title) => title.clone(),
+            None => uname().nodename().to_owned(),
+        };
+
+        Ok(Self {
+            tty: cursor::HideCursor::from(tty),
+            size: (0, 0),
+            screen: config.screen.clone(),
+            rows: Vec::new(),
+            body: Rect::default(),
+            banner: Label::new(&banner, (0, 0)).with_role(Role::Title),
+            clock: Clock::new(&config.screen.clock_format),
             login_form,
             messages,
+            keymap: config.keys.clone(),
+            power_actions,
+            confirm: None,
+            status: None,
+            theme,
+            borders,
+            events: EventLoop::new()?,
+        })
+    }
+
+    /// Place everything for the current terminal size.
+    fn layout(&mut self) {
+        let size = termion::terminal_size().unwrap_or(self.size);
+        self.resize(size);
+    }
+
+    /// Place everything for a terminal of `size`.
+    fn resize(&mut self, size: (u16, u16)) {
+        self.size = size;
+        let (width, height) = size;
+
+        // Leave out the other rows if they would take the last row of the form
+        let rows = if self.has_rows() {
+            self.screen.rows.clone()
+        } else {
+            vec![ScreenRow::Form]
         };
+        let constraints: Vec<_> = rows
+            .iter()
+            .map(|row| match row {
+                ScreenRow::Form => Constraint::Fill(1),
+                _ => Constraint::Fixed(1),
+            })
+            .collect();
+        let areas = Layout::rows(&constraints).split(Rect::new((0, 0), (width, height)));
+        self.rows = rows.into_iter().zip(areas).collect();
+
+        for &(row, area) in self.rows.iter() {
+            match row {
+                ScreenRow::Form => self.body = area,
+                ScreenRow::Banner => {
+                    self.banner.set_length(area.width as usize);
+                    let size = (self.banner.width() as u16, 1);
+                    let banner = area.align(size, Align::Center, Align::Start);
+                    self.banner.set_position(banner.position());
+                }
+                ScreenRow::Clock => self.clock.set_area(area),
+                ScreenRow::Keys | ScreenRow::Status => {}
+            }
+        }
 
-        ui.clear();
-        ui.redraw();
+        self.login_form.fit(self.body.size());
+        let (horizontal, vertical) = self.screen.form.align();
+        let form = self
+            .body
+            .align(self.login_form.size(), horizontal, vertical);
+        self.login_form.set_position(form.position());
+        // Use the space below the form for the user list, including its border
+        let completion_rows = (self.body.y + self.body.height)
+            .saturating_sub(form.y + form.height)
+            .saturating_sub(2)
+            .min(MAX_COMPLETION_ROWS);
+        self.login_form.set_completion_rows(completion_rows);
+
+        if let Some((_, ref mut dialog)) = self.confirm {
+            dialog.fit(width);
+            dialog.set_position(center(self.size, dialog.size()));
+        }
+    }
 
-        Ok(ui)
+    /// Whether the configured rows fit next to at least one row of the form.
+    fn has_rows(&self) -> bool {
+        self.size.1 as usize >= self.screen.rows.len()
     }
 
-    pub fn handle_input(&mut self) -> Result<LoginAction, LoginError> {
-        let stdin = io::stdin();
-        let stdin = stdin.lock();
+    /// Whether messages are shown as boxes instead of in the status line.
+    fn has_message_boxes(&self) -> bool {
+        // Boxed messages only fit next to the full form
+        self.screen.messages && self.login_form.layout() == FormLayout::Full
+    }
 
-        self.redraw();
+    fn request_confirmation(&mut self, action: PowerAction) {
+        let title = format!("Confirm {}", action.label());
+        let mut dialog = Dialog::new(&title, action.question(), self.borders.dialog);
+        dialog.fit(self.size.0);
 
-        for evt in stdin.keys() {
-            let key = evt?;
+        dialog.set_position(center(self.size, dialog.size()));
+        self.confirm = Some((action, dialog));
+    }
 
-            match key {
-                Key::Esc => {
-                    // DEBUG: Exit
-                    break;
-                }
-                Key::F(1) => {
-                    // DEBUG: Clear and Redraw
-                    self.reset();
-                    return Ok(LoginAction::Cancel);
-                }
-                Key::F(3) => {
-                    self.login_form.toggle();
-                }
-                Key::Left => {
-                    self.login_form.cursor_left();
-                }
-                Key::Right => {
-                    self.login_form.cursor_right();
-                }
-                Key::Up => {
-                    self.login_form.focus_prev();
-                }
-                Key::Down => {
-                    self.login_form.focus_next();
-                }
-                Key::Home => {
-                    self.login_form.cursor_start();
-                }
-                Key::End => {
-                    self.login_form.cursor_end();
-                }
-                Key::Char(c) => {
-                    if !c.is_control() {
-                        self.login_form.push(c);
-                    }
-                    match c {
-                        '\t' => {} //self.login_form.focus_next(),
-                        '\n' => match self.login_form.focus() {
-                            FormElement::Prompt => {
-                                return Ok(LoginAction::Submit(self.login_form.to_string()));
-                            }
-                        },
-                        _ => {}
-                    };
-                }
-                Key::Backspace => {
-                    self.login_form.pop(false);
-                }
-                Key::Delete => {
-                    self.login_form.pop(true);
+    fn handle_action(&mut self, action: Action) -> Option<LoginAction> {
+        if let Some(&(_, power)) = self.power_actions.iter().find(|(a, _)| *a == action) {
+            self.request_confirmation(power);
+            return None;
+        }
+
+        match action {
+            Action::Quit => return Some(LoginAction::Quit),
+            Action::Cancel => {
+                self.reset();
+                return Some(LoginAction::Cancel);
+            }
+            Action::Submit => match self.login_form.focus() {
+                FormElement::Prompt => {
+                    self.login_form.accept_completion();
+                    return Some(LoginAction::Submit(
+                        self.login_form.to_string(),
+                        self.login_form.session(),
+                    ));
                 }
-                _ => {}
-            };
+                FormElement::Session => self.login_form.focus_next(),
+            },
+            Action::Complete => self.login_form.complete(),
+            Action::ToggleMask => self.login_form.toggle(),
+            Action::FocusPrev => self.login_form.focus_prev(),
+            Action::FocusNext => self.login_form.focus_next(),
+            Action::CursorLeft => self.login_form.cursor_left(),
+            Action::CursorRight => self.login_form.cursor_right(),
+            Action::CursorStart => self.login_form.cursor_start(),
+            Action::CursorEnd => self.login_form.cursor_end(),
+            Action::DeleteLeft => self.login_form.pop(false),
+            Action::DeleteRight => self.login_form.pop(true),
+            // Disabled power actions
+            Action::PowerOff | Action::Reboot | Action::Suspend | Action::Hibernate => {}
+        };
 
-            self.redraw();
-        }
+        None
+    }
+
+    fn handle_confirmation(&mut self, key: Key) -> Option<LoginAction> {
+        let action = self.keymap.action(key);
+        let dialog = &mut self.confirm.as_mut()?.1;
+        let confirmed = match (key, action) {
+            (Key::Char('y'), _) | (Key::Char('Y'), _) => true,
+            (Key::Char('n'), _) | (Key::Char('N'), _) => false,
+            (_, Some(Action::Submit)) => dialog.is_confirmed(),
+            (_, Some(Action::Quit)) | (_, Some(Action::Cancel)) => false,
+            (_, Some(Action::CursorLeft))
+            | (_, Some(Action::CursorRight))
+            | (_, Some(Action::FocusPrev))
+            | (_, Some(Action::FocusNext)) => {
+                dialog.toggle();
+                return None;
+            }
+            _ => return None,
+        };
+
+        let (action, _) = self.confirm.take()?;
+        self.clear();
 
-        Ok(LoginAction::Quit)
+        if confirmed {
+            Some(action.into())
+        } else {
+            None
+        }
     }
-}
 
-impl<T: Write> TerminalUI<T> {
     pub fn reset(&mut self) {
         self.clear();
         self.messages.clear();
+        self.events.cancel(Timer::ExpireMessages);
         self.login_form.reset();
+        self.login_form.set_user(None);
     }
 
-    fn add_message(&mut self, title: &str, message: &str) {
-        let msg = Message::new(title, message, (0, 0));
+    fn add_message(&mut self, title: &str, message: &str, role: Role) {
+        let msg = Message::new(title, message, role, self.borders.message, (0, 0));
         self.messages.push(msg);
+        self.events.schedule(Timer::ExpireMessages, MESSAGE_TIMEOUT);
     }
 
     fn clear(&mut self) {
         draw2tty!(
             self.tty,
-            "{}{}{}{}",
+            "{}{}{}{}{}",
+            self.theme.plain(),
             cursor::Goto(1, 1),
             clear::All,
             cursor::BlinkingBlock,
@@ -153,34 +315,238 @@ impl<T: Write> TerminalUI<T> {
     }
 
     fn redraw(&mut self) {
-        draw2tty!(self.tty, "{}", cursor::Goto(1, 1));
-        draw2tty!(
-            self.tty,
-            "{}{}{}",
-            color::Bg(color::Red),
-            "F1 shutdown",
-            color::Bg(color::Reset)
-        );
-        draw2tty!(self.tty, "{}", cursor::Right(3));
+        for (row, area) in self.rows.clone() {
+            match row {
+                ScreenRow::Keys => self.draw_top_bar(area.y),
+                ScreenRow::Banner => self.banner.draw(&mut self.tty, &self.theme, (1, 1), false),
+                ScreenRow::Clock => self.clock.draw(&mut self.tty, &self.theme, (1, 1), false),
+                ScreenRow::Status | ScreenRow::Form => {}
+            }
+        }
+
+        self.login_form
+            .draw(&mut self.tty, &self.theme, (1, 1), true);
+
+        if self.has_message_boxes() {
+            self.draw_messages();
+        }
+
+        if let Some((_, ref dialog)) = self.confirm {
+            dialog.draw(&mut self.tty, &self.theme, (1, 1), true);
+        }
+
+        self.draw_status_line();
+
+        draw2tty!(self.tty, "{}", cursor::Restore);
+        self.tty.flush().expect("Cannot flush stdout");
+    }
+
+    fn draw_top_bar(&mut self, row: u16) {
+        let (tty, keymap, theme) = (&mut self.tty, &self.keymap, &self.theme);
+        draw2tty!(tty, "{}{}", cursor::Goto(1, row + 1), clear::CurrentLine);
+        let entries = self
+            .power_actions
+            .iter()
+            .filter_map(|&(action, power)| Some((keymap.key(action)?, power.label())));
+        // Leave out what does not fit, the terminal would wrap it
+        let mut space = self.size.0 as usize;
+        for (idx, (key, label)) in entries.enumerate() {
+            let gap = if idx > 0 { 3 } else { 0 };
+            let len = gap + key_name(key).chars().count() + 1 + label.chars().count();
+            if len > space {
+                break;
+            }
+            space -= len;
+
+            if idx > 0 {
+                draw2tty!(tty, "{}", cursor::Right(3));
+            }
+            draw2tty!(
+                tty,
+                "{}{}{} {}",
+                theme.paint(Role::Keys),
+                key_name(key),
+                theme.plain(),
+                label
+            );
+        }
+    }
+
+    fn draw_messages(&mut self) {
+        // Show as many of the latest messages as fit, away from the form
+        let area = self.body.inset(Margin {
+            top: 1,
+            right: 1,
+            left: 1,
+            ..Margin::default()
+        });
+        let (horizontal, vertical) = match self.screen.form {
+            FormPlacement::Left => (Align::End, Align::Start),
+            FormPlacement::Top => (Align::Start, Align::End),
+            _ => (Align::Start, Align::Start),
+        };
+
+        let mut space = area.height;
+        let shown = self
+            .messages
+            .iter()
+            .rev()
+            .take_while(|message| match space.checked_sub(message.size().1) {
+                Some(left) => {
+                    space = left;
+                    true
+                }
+                None => false,
+            })
+            .count();
+
+        let first = self.messages.len() - shown;
+        let heights: Vec<_> = self.messages[first..]
+            .iter()
+            .map(|message| Constraint::Fixed(message.size().1))
+            .collect();
+        let rows = Layout::rows(&heights).align(vertical).split(area);
+        for (message, row) in self.messages[first..].iter_mut().zip(rows) {
+            let position = row
+                .align(message.size(), horizontal, Align::Start)
+                .position();
+            message.set_position(position);
+            message.draw(&mut self.tty, &self.theme, (1, 1), false);
+        }
+    }
+
+    fn draw_clock(&mut self) {
+        if self.rows.iter().any(|&(row, _)| row == ScreenRow::Clock) {
+            self.clock.draw(&mut self.tty, &self.theme, (1, 1), false);
+            draw2tty!(self.tty, "{}", cursor::Restore);
+            self.tty.flush().expect("Cannot flush stdout");
+        }
+        self.events.schedule(Timer::Clock, self.clock.next_tick());
+    }
+
+    fn draw_status_line(&mut self) {
+        let (width, height) = self.size;
+        if height == 0 {
+            return;
+        }
+        // Without boxes the latest message takes the status line
+        let (text, role) = match (&self.status, self.messages.last()) {
+            (Some(status), _) => (status.as_str(), Role::Status),
+            (None, Some(message)) if !self.has_message_boxes() => {
+                (message.summary(), message.role())
+            }
+            _ => ("", Role::Background),
+        };
+        let row = self
+            .rows
+            .iter()
+            .find(|&&(row, _)| row == ScreenRow::Status)
+            .map(|&(_, area)| area.y);
+        if text.is_empty() && row.is_none() {
+            // Without a row of its own, the form may be drawn on the last row
+            return;
+        }
+
+        let text: String = text.chars().take(width as usize).collect();
         draw2tty!(
             self.tty,
-            "{}{}{}",
-            color::Fg(color::Green),
-            "F2 reboot",
-            color::Fg(color::Reset)
+            "{}{}{}{}{}",
+            cursor::Goto(1, row.unwrap_or(height - 1) + 1),
+            clear::CurrentLine,
+            self.theme.paint(role),
+            text,
+            self.theme.plain()
         );
+    }
+}
+
+impl LoginUI for TerminalUI<raw::RawTerminal<io::Stdout>> {
+    fn handle_input(&mut self, socket: RawFd) -> Result<LoginAction, LoginError> {
+        self.redraw();
 
-        self.login_form.draw(&mut self.tty, (1, 1), true);
+        loop {
+            let key = match self.events.wait(socket)? {
+                Event::Key(key) => key,
+                Event::Timer(Timer::ExpireMessages) => {
+                    self.messages.clear();
+                    self.clear();
+                    self.redraw();
+                    continue;
+                }
+                Event::Timer(Timer::Clock) => {
+                    self.draw_clock();
+                    continue;
+                }
+                Event::Timer(Timer::Reconnect) => return Ok(LoginAction::Reconnect),
+                Event::Resize => {
+                    self.layout();
+                    self.clear();
+                    self.redraw();
+                    continue;
+                }
+                Event::Terminate => return Ok(LoginAction::Quit),
+                Event::Disconnected => return Ok(LoginAction::Disconnected),
+            };
+
+            if self.confirm.is_some() {
+                if let Some(action) = self.handle_confirmation(key) {
+                    return Ok(action);
+                }
+                self.redraw();
+                continue;
+            }
+
+            match self.keymap.action(key) {
+                Some(action) => {
+                    if let Some(action) = self.handle_action(action) {
+                        return Ok(action);
+                    }
+                }
+                None => {
+                    if let Key::Char(c) = key {
+                        if !c.is_control() {
+                            self.login_form.push(c);
+                        }
+                    }
+                }
+            };
 
-        let message_left = 2;
-        let mut message_top = 3;
-        for message in self.messages.iter() {
-            message.draw(&mut self.tty, (message_left, message_top), false);
-            message_top += message.height();
+            self.redraw();
         }
+    }
 
-        draw2tty!(self.tty, "{}", cursor::Restore);
-        self.tty.flush().expect("Cannot flush stdout");
+    fn set_sessions(&mut self, sessions: &[Session], selected: usize) {
+        let names = sessions.iter().map(|s| s.name.clone()).collect();
+        self.login_form.set_sessions(names, selected);
+    }
+
+    fn set_users(&mut self, users: &[User]) {
+        let users = users
+            .iter()
+            .map(|user| (user.name.clone(), user.display_name().to_owned()))
+            .collect();
+        self.login_form.set_users(users);
+    }
+
+    fn set_user(&mut self, user: Option<&str>) {
+        self.login_form.set_user(user);
+    }
+
+    fn select_session(&mut self, index: usize) {
+        self.login_form.select_session(index);
+    }
+
+    fn selected_session(&self) -> usize {
+        self.login_form.session()
+    }
+
+    fn set_status(&mut self, status: Option<&str>) {
+        self.status = status.map(str::to_owned);
+        self.redraw();
+    }
+
+    fn schedule_reconnect(&mut self, after: Duration) {
+        self.events.schedule(Timer::Reconnect, after);
     }
 }
 
@@ -196,11 +562,11 @@ impl<T: Write> GreetUI for TerminalUI<T> {
     }
 
     fn show_info_message(&mut self, message: &str) {
-        self.add_message("Info:", message);
+        self.add_message("Info:", message, Role::Info);
     }
 
     fn show_error_message(&mut self, message: &str) {
-        self.add_message("Error:", message);
+        self.add_message("Error:", message, Role::Error);
     }
 
     fn show_authentication_failure(&mut self, reason: &str) {
@@ -208,14 +574,94 @@ impl<T: Write> GreetUI for TerminalUI<T> {
         self.add_message(
             "Authentication failed!",
             format!("Reason: {}", reason).as_str(),
+            Role::Error,
+        );
+    }
+
+    fn show_session_failure(&mut self, reason: &str) {
+        self.reset();
+        self.add_message(
+            "Login failed!",
+            format!("Reason: {}", reason).as_str(),
+            Role::Error,
         );
     }
 }
 
 impl<T: Write> Drop for TerminalUI<T> {
     fn drop(&mut self) {
-        let (_, height) = termion::terminal_size().unwrap();
-        draw2tty!(self.tty, "{}", cursor::Goto(1, height));
-        //draw2tty!(self.tty, "{}{}", cursor::Goto(1, 1), clear::All);
+        let _ = self.tty.flush();
+        // Keeps the screen if a panic already restored the terminal
+        terminal::restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::power;
+
+    #[test]
+    fn shows_failed_power_actions() {
+        let mut config = Config::default();
+        config.power.poweroff = Some("true".to_owned());
+        config.power.reboot = Some("false".to_owned());
+        let mut ui = TerminalUI::new(Vec::new(), &config, true).unwrap();
+        ui.resize((80, 24));
+
+        ui.request_confirmation(PowerAction::Reboot);
+        assert!(ui.handle_confirmation(Key::Char('n')).is_none());
+        assert!(ui.confirm.is_none());
+
+        let cases = [
+            (PowerAction::PowerOff, None),
+            (PowerAction::Reboot, Some("'false' failed: exit status: 1")),
+        ];
+        for &(action, error) in cases.iter() {
+            ui.request_confirmation(action);
+            let confirmed = match ui.handle_confirmation(Key::Char('y')) {
+                Some(LoginAction::PowerOff) => PowerAction::PowerOff,
+                Some(LoginAction::Reboot) => PowerAction::Reboot,
+                other => panic!("Unexpected action {:?}", other),
+            };
+            assert_eq!(confirmed, action);
+
+            // As done by the login loop
+            if let Err(err) = power::run(&config.power, confirmed) {
+                ui.show_error_message(&err.to_string());
+            }
+            ui.tty.clear();
+            ui.redraw();
+            let drawn = String::from_utf8_lossy(&ui.tty).into_owned();
+            match error {
+                Some(error) => assert!(drawn.contains(error), "{:?}", drawn),
+                None => assert!(!drawn.contains("Error:"), "{:?}", drawn),
+            }
+        }
+    }
+
+    #[test]
+    fn draws_on_tiny_terminals() {
+        let mut config = Config::default();
+        config.screen.rows = vec![
+            ScreenRow::Keys,
+            ScreenRow::Banner,
+            ScreenRow::Clock,
+            ScreenRow::Form,
+            ScreenRow::Status,
+        ];
+        let mut ui = TerminalUI::new(Vec::new(), &config, true).unwrap();
+        ui.show_error_message("Shown on the status line of tiny terminals");
+        ui.request_confirmation(PowerAction::PowerOff);
+
+        for &size in [(0, 0), (1, 1), (24, 2), (80, 3), (80, 24), (0, 0)].iter() {
+            for status in [None, Some("Reconnecting")] {
+                ui.status = status.map(str::to_owned);
+                ui.resize(size);
+                ui.clear();
+                ui.redraw();
+            }
+        }
     }
 }
@@ -0,0 +1,313 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use termion::event::Key;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Quit,
+    Cancel,
+    Submit,
+    Complete,
+    ToggleMask,
+    PowerOff,
+    Reboot,
+    Suspend,
+    Hibernate,
+    FocusPrev,
+    FocusNext,
+    CursorLeft,
+    CursorRight,
+    CursorStart,
+    CursorEnd,
+    DeleteLeft,
+    DeleteRight,
+}
+
+const ACTION_NAMES: [(&str, Action); 17] = [
+    ("quit", Action::Quit),
+    ("cancel", Action::Cancel),
+    ("submit", Action::Submit),
+    ("complete", Action::Complete),
+    ("toggle-mask", Action::ToggleMask),
+    ("poweroff", Action::PowerOff),
+    ("reboot", Action::Reboot),
+    ("suspend", Action::Suspend),
+    ("hibernate", Action::Hibernate),
+    ("focus-prev", Action::FocusPrev),
+    ("focus-next", Action::FocusNext),
+    ("cursor-left", Action::CursorLeft),
+    ("cursor-right", Action::CursorRight),
+    ("cursor-start", Action::CursorStart),
+    ("cursor-end", Action::CursorEnd),
+    ("delete-left", Action::DeleteLeft),
+    ("delete-right", Action::DeleteRight),
+];
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, _) = ACTION_NAMES
+            .iter()
+            .find(|(_, action)| action == self)
+            .expect("Action has no name");
+
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ACTION_NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, action)| *action)
+            .ok_or_else(|| format!("unknown action `{}`", name))
+    }
+}
+
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+const DEFAULT_BINDINGS: [(Key, Action); 19] = [
+    (Key::Esc, Action::Quit),
+    (Key::Ctrl('c'), Action::Cancel),
+    (Key::Char('\n'), Action::Submit),
+    (Key::Char('\t'), Action::Complete),
+    (Key::F(3), Action::ToggleMask),
+    (Key::F(1), Action::PowerOff),
+    (Key::F(2), Action::Reboot),
+    (Key::F(4), Action::Suspend),
+    (Key::F(5), Action::Hibernate),
+    (Key::Up, Action::FocusPrev),
+    (Key::Down, Action::FocusNext),
+    (Key::Left, Action::CursorLeft),
+    (Key::Right, Action::CursorRight),
+    (Key::Home, Action::CursorStart),
+    (Key::End, Action::CursorEnd),
+    (Key::Ctrl('a'), Action::CursorStart),
+    (Key::Ctrl('e'), Action::CursorEnd),
+    (Key::Backspace, Action::DeleteLeft),
+    (Key::Delete, Action::DeleteRight),
+];
+
+/// Parse key names like `F1`, `Esc`, `Ctrl-c` or `a`.
+pub fn parse_key(name: &str) -> Result<Key, String> {
+    let single_char = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    let key = match name {
+        "Esc" => Key::Esc,
+        "Enter" => Key::Char('\n'),
+        "Tab" => Key::Char('\t'),
+        "BackTab" => Key::BackTab,
+        "Space" => Key::Char(' '),
+        "Backspace" => Key::Backspace,
+        "Delete" => Key::Delete,
+        "Insert" => Key::Insert,
+        "Left" => Key::Left,
+        "Right" => Key::Right,
+        "Up" => Key::Up,
+        "Down" => Key::Down,
+        "Home" => Key::Home,
+        "End" => Key::End,
+        "PageUp" => Key::PageUp,
+        "PageDown" => Key::PageDown,
+        _ => {
+            if let Some(n) = name.strip_prefix('F').and_then(|n| n.parse().ok()) {
+                if (1..=12).contains(&n) {
+                    return Ok(Key::F(n));
+                }
+            } else if let Some(c) = name.strip_prefix("Ctrl-").and_then(single_char) {
+                return Ok(Key::Ctrl(c.to_ascii_lowercase()));
+            } else if let Some(c) = name.strip_prefix("Alt-").and_then(single_char) {
+                return Ok(Key::Alt(c));
+            } else if let Some(c) = single_char(name) {
+                return Ok(Key::Char(c));
+            }
+            return Err(format!("unknown key `{}`", name));
+        }
+    };
+
+    Ok(key)
+}
+
+/// Format `key` the way it is written in the configuration.
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Esc => "Esc".to_owned(),
+        Key::Char('\n') => "Enter".to_owned(),
+        Key::Char('\t') => "Tab".to_owned(),
+        Key::Char(' ') => "Space".to_owned(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("Ctrl-{}", c),
+        Key::Alt(c) => format!("Alt-{}", c),
+        Key::F(n) => format!("F{}", n),
+        Key::PageUp => "PageUp".to_owned(),
+        Key::PageDown => "PageDown".to_owned(),
+        _ => format!("{:?}", key),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyNames {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::One(name) => vec![name],
+            Self::Many(names) => names,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: DEFAULT_BINDINGS.to_vec(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, action)| *action)
+    }
+
+    /// The first key bound to `action`, if any.
+    pub fn key(&self, action: Action) -> Option<Key> {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(key, _)| *key)
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let overrides = BTreeMap::<Action, KeyNames>::deserialize(deserializer)?;
+
+        // Every action given in the config replaces all of its default bindings
+        let mut keymap = Self::default();
+        for (action, names) in overrides {
+            keymap.bindings.retain(|(_, a)| *a != action);
+            for name in names.into_vec() {
+                let key = parse_key(&name).map_err(de::Error::custom)?;
+                keymap.bindings.push((key, action));
+            }
+        }
+
+        for (idx, (key, action)) in keymap.bindings.iter().enumerate() {
+            if let Some((_, other)) = keymap.bindings[..idx].iter().find(|(k, _)| k == key) {
+                return Err(de::Error::custom(format!(
+                    "key `{}` is bound to both `{}` and `{}`",
+                    key_name(*key),
+                    other,
+                    action
+                )));
+            }
+        }
+
+        Ok(keymap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(content: &str) -> Result<Keymap, String> {
+        toml::from_str(content).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn parses_key_names() {
+        let cases = [
+            ("a", Key::Char('a')),
+            ("Enter", Key::Char('\n')),
+            ("Space", Key::Char(' ')),
+            ("F12", Key::F(12)),
+            ("Ctrl-C", Key::Ctrl('c')),
+            ("Alt-x", Key::Alt('x')),
+            ("PageDown", Key::PageDown),
+            ("BackTab", Key::BackTab),
+        ];
+        for &(name, key) in cases.iter() {
+            assert_eq!(parse_key(name), Ok(key), "{}", name);
+        }
+        for name in ["", "F0", "F13", "Ctrl-", "Ctrl-ab", "Hyper-a", "ab"].iter() {
+            assert!(parse_key(name).is_err(), "{}", name);
+        }
+        for name in ["Esc", "Tab", "F1", "Ctrl-a", "Alt-x", "PageUp", "q"].iter() {
+            assert_eq!(key_name(parse_key(name).unwrap()), *name);
+        }
+    }
+
+    #[test]
+    fn overrides_bindings_per_action() {
+        let keymap =
+            keymap("poweroff = \"F10\"\ncursor-start = [\"Home\", \"Ctrl-b\"]\nquit = []\n")
+                .unwrap();
+
+        assert_eq!(keymap.action(Key::F(10)), Some(Action::PowerOff));
+        assert_eq!(keymap.action(Key::F(1)), None);
+        assert_eq!(keymap.key(Action::PowerOff), Some(Key::F(10)));
+        assert_eq!(keymap.action(Key::Ctrl('b')), Some(Action::CursorStart));
+        assert_eq!(keymap.action(Key::Ctrl('a')), None);
+        assert_eq!(keymap.key(Action::Quit), None);
+        // Other actions keep their defaults
+        assert_eq!(keymap.action(Key::F(2)), Some(Action::Reboot));
+    }
+
+    #[test]
+    fn rejects_invalid_bindings() {
+        let err = keymap("reboot = \"F1\"\n").unwrap_err();
+        assert!(
+            err.contains("key `F1` is bound to both `poweroff` and `reboot`"),
+            "{}",
+            err
+        );
+        // Rebinding the default key of another action frees it first
+        assert!(keymap("reboot = \"F1\"\npoweroff = \"F10\"\n").is_ok());
+
+        assert!(keymap("shutdown = \"F1\"\n")
+            .unwrap_err()
+            .contains("unknown action `shutdown`"));
+        assert!(keymap("quit = \"Escape\"\n")
+            .unwrap_err()
+            .contains("unknown key `Escape`"));
+    }
+}
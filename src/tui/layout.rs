@@ -0,0 +1,336 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use serde::Deserialize;
+
+/// Placement of something smaller than the space it is given.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    /// Left or top.
+    #[serde(alias = "left", alias = "top")]
+    Start,
+    Center,
+    /// Right or bottom.
+    #[serde(alias = "right", alias = "bottom")]
+    End,
+}
+
+impl Align {
+    /// Offset of `length` cells inside `space` cells.
+    pub fn offset(self, length: u16, space: u16) -> u16 {
+        let free = space.saturating_sub(length);
+        match self {
+            Self::Start => 0,
+            Self::Center => free / 2,
+            Self::End => free,
+        }
+    }
+}
+
+/// Space kept free around the edges of an area.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Margin {
+    pub top: u16,
+    pub right: u16,
+    pub bottom: u16,
+    pub left: u16,
+}
+
+impl Margin {
+    /// `horizontal` columns on the left and right, `vertical` rows above and below.
+    pub fn symmetric(horizontal: u16, vertical: u16) -> Self {
+        Self {
+            top: vertical,
+            right: horizontal,
+            bottom: vertical,
+            left: horizontal,
+        }
+    }
+}
+
+/// An area on screen, positions are 0-based like the ones of the widgets.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(position: (u16, u16), size: (u16, u16)) -> Self {
+        Self {
+            x: position.0,
+            y: position.1,
+            width: size.0,
+            height: size.1,
+        }
+    }
+
+    pub fn position(&self) -> (u16, u16) {
+        (self.x, self.y)
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// The area left inside `margin`.
+    pub fn inset(&self, margin: Margin) -> Self {
+        let width = self.width.saturating_sub(margin.left + margin.right);
+        let height = self.height.saturating_sub(margin.top + margin.bottom);
+        Self {
+            x: self.x + margin.left.min(self.width),
+            y: self.y + margin.top.min(self.height),
+            width,
+            height,
+        }
+    }
+
+    /// A box of `size` placed inside, shrunk to fit if needed.
+    pub fn align(&self, size: (u16, u16), horizontal: Align, vertical: Align) -> Self {
+        let (width, height) = (size.0.min(self.width), size.1.min(self.height));
+        Self {
+            x: self.x + horizontal.offset(width, self.width),
+            y: self.y + vertical.offset(height, self.height),
+            width,
+            height,
+        }
+    }
+}
+
+/// Space a row or column takes in a `Layout`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Constraint {
+    /// Exactly this many cells.
+    Fixed(u16),
+    /// At least this many cells, growing like `Fill(1)` with space to spare.
+    Min(u16),
+    /// A share of the whole area in percent.
+    Percentage(u16),
+    /// A share of the space left, weighted against the other fills.
+    Fill(u16),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Direction {
+    Rows,
+    Columns,
+}
+
+/// Splits an area into rows or columns following constraints.
+///
+/// Fixed sizes, percentages and minimums are handed out first, what is left
+/// goes to `Fill` and `Min` by weight. If the area is too small, the last
+/// parts are shrunk first. Without anything to fill, the parts are placed
+/// by `align`.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    direction: Direction,
+    constraints: Vec<Constraint>,
+    margin: Margin,
+    spacing: u16,
+    align: Align,
+}
+
+impl Layout {
+    /// Parts stacked from top to bottom.
+    pub fn rows(constraints: &[Constraint]) -> Self {
+        Self::new(Direction::Rows, constraints)
+    }
+
+    /// Parts placed from left to right.
+    pub fn columns(constraints: &[Constraint]) -> Self {
+        Self::new(Direction::Columns, constraints)
+    }
+
+    fn new(direction: Direction, constraints: &[Constraint]) -> Self {
+        Self {
+            direction,
+            constraints: constraints.to_vec(),
+            margin: Margin::default(),
+            spacing: 0,
+            align: Align::Start,
+        }
+    }
+
+    pub fn margin(mut self, margin: Margin) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Keep `spacing` cells between the parts.
+    pub fn spacing(mut self, spacing: u16) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// One area per constraint, in order.
+    pub fn split(&self, area: Rect) -> Vec<Rect> {
+        let area = area.inset(self.margin);
+        let (start, space) = match self.direction {
+            Direction::Rows => (area.y, area.height),
+            Direction::Columns => (area.x, area.width),
+        };
+
+        let lengths = self.lengths(space);
+        let used = lengths.iter().sum::<u16>() + self.gaps(space);
+        let mut offset = start + self.align.offset(used, space);
+
+        lengths
+            .into_iter()
+            .map(|length| {
+                let rect = match self.direction {
+                    Direction::Rows => Rect::new((area.x, offset), (area.width, length)),
+                    Direction::Columns => Rect::new((offset, area.y), (length, area.height)),
+                };
+                offset += length + self.spacing;
+                rect
+            })
+            .collect()
+    }
+
+    /// Total spacing, limited to `space`.
+    fn gaps(&self, space: u16) -> u16 {
+        let count = self.constraints.len().saturating_sub(1) as u32;
+        (u32::from(self.spacing) * count).min(u32::from(space)) as u16
+    }
+
+    fn lengths(&self, space: u16) -> Vec<u16> {
+        let available = space - self.gaps(space);
+
+        let mut lengths: Vec<u16> = self
+            .constraints
+            .iter()
+            .map(|constraint| match *constraint {
+                Constraint::Fixed(length) | Constraint::Min(length) => length,
+                Constraint::Percentage(percent) => {
+                    (u32::from(available) * u32::from(percent.min(100)) / 100) as u16
+                }
+                Constraint::Fill(_) => 0,
+            })
+            .collect();
+
+        // Shrink from the end until everything fits
+        let mut excess = lengths
+            .iter()
+            .map(|&l| u32::from(l))
+            .sum::<u32>()
+            .saturating_sub(u32::from(available));
+        for length in lengths.iter_mut().rev() {
+            let cut = (*length as u32).min(excess);
+            *length -= cut as u16;
+            excess -= cut;
+        }
+
+        let weights: Vec<u32> = self
+            .constraints
+            .iter()
+            .map(|constraint| match *constraint {
+                Constraint::Fill(weight) => u32::from(weight),
+                Constraint::Min(_) => 1,
+                _ => 0,
+            })
+            .collect();
+        let total_weight: u32 = weights.iter().sum();
+        let left = u32::from(available - lengths.iter().sum::<u16>());
+        if total_weight == 0 || left == 0 {
+            return lengths;
+        }
+
+        // Hand out the rest by weight, rounding errors go to the last fill
+        let mut handed_out = 0;
+        let last = weights.iter().rposition(|&w| w > 0).expect("no fill");
+        for (idx, (length, &weight)) in lengths.iter_mut().zip(weights.iter()).enumerate() {
+            let share = if idx == last {
+                left - handed_out
+            } else {
+                left * weight / total_weight
+            };
+            handed_out += share;
+            *length += share as u16;
+        }
+
+        lengths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Constraint::*;
+
+    fn lengths(layout: Layout, space: u16) -> Vec<u16> {
+        layout
+            .split(Rect::new((0, 0), (space, 1)))
+            .iter()
+            .map(|rect| rect.width)
+            .collect()
+    }
+
+    #[test]
+    fn hands_out_space_by_constraint() {
+        let layout = Layout::columns(&[Fixed(10), Percentage(25), Min(5), Fill(2)]);
+        // 10 fixed, 20 for 25 percent, 5 minimum and 45 left for a weight of 3
+        assert_eq!(lengths(layout, 80), vec![10, 20, 5 + 15, 30]);
+    }
+
+    #[test]
+    fn shrinks_the_last_parts_first() {
+        let layout = Layout::columns(&[Fixed(10), Min(8), Fixed(6), Fill(1)]);
+        assert_eq!(lengths(layout.clone(), 20), vec![10, 8, 2, 0]);
+        assert_eq!(lengths(layout.clone(), 5), vec![5, 0, 0, 0]);
+        assert_eq!(lengths(layout, 0), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn places_rows_with_margin_and_spacing() {
+        let rows = Layout::rows(&[Fixed(1), Fill(1), Fixed(2)])
+            .margin(Margin::symmetric(2, 1))
+            .spacing(1)
+            .split(Rect::new((5, 5), (20, 12)));
+        assert_eq!(
+            rows,
+            vec![
+                Rect::new((7, 6), (16, 1)),
+                Rect::new((7, 8), (16, 5)),
+                Rect::new((7, 14), (16, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn aligns_parts_without_fill() {
+        let area = Rect::new((0, 0), (10, 1));
+        let layout = Layout::columns(&[Fixed(2), Fixed(2)]).spacing(2);
+        let starts =
+            |layout: Layout| -> Vec<u16> { layout.split(area).iter().map(|rect| rect.x).collect() };
+        assert_eq!(starts(layout.clone()), vec![0, 4]);
+        assert_eq!(starts(layout.clone().align(Align::Center)), vec![2, 6]);
+        assert_eq!(starts(layout.align(Align::End)), vec![4, 8]);
+    }
+
+    #[test]
+    fn aligns_boxes_in_an_area() {
+        let area = Rect::new((1, 1), (20, 10));
+        assert_eq!(
+            area.align((8, 4), Align::Center, Align::End),
+            Rect::new((7, 7), (8, 4))
+        );
+        assert_eq!(
+            area.align((30, 4), Align::End, Align::Start),
+            Rect::new((1, 1), (20, 4))
+        );
+    }
+}
@@ -0,0 +1,84 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use nix::libc;
+use nix::sys::termios;
+use nix::unistd;
+use std::env;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, OnceLock};
+use std::thread;
+
+const STDOUT: libc::c_int = 1;
+
+/// Clear the screen, reset all attributes and show the cursor.
+const RESET: &[u8] = b"\x1B[0m\x1B[H\x1B[2J\x1B[?25h";
+
+/// Terminal attributes from before switching to raw mode.
+static ORIGINAL: OnceLock<libc::termios> = OnceLock::new();
+/// Whether the terminal still needs to be restored.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Remember the current terminal state for `restore`.
+///
+/// Also installs a panic hook restoring the terminal before the panic
+/// message is printed, so it does not get lost on the login screen.
+pub fn save() -> nix::Result<()> {
+    if ORIGINAL.get().is_none() {
+        let _ = ORIGINAL.set(termios::tcgetattr(STDOUT)?.into());
+    }
+    ACTIVE.store(true, Ordering::SeqCst);
+
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let ui_thread = thread::current().id();
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            // Panics of helper threads do not end the login screen
+            if thread::current().id() == ui_thread {
+                restore();
+            }
+            default_hook(info);
+        }));
+    });
+
+    Ok(())
+}
+
+/// Whether the locale uses UTF-8 and box drawing characters can be shown.
+pub fn is_utf8_locale() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    // Locales are written as language_TERRITORY.codeset@modifier
+    let codeset = locale
+        .split('@')
+        .next()
+        .and_then(|locale| locale.split('.').nth(1));
+    codeset == Some("utf-8") || codeset == Some("utf8")
+}
+
+/// Leave raw mode, clear the screen and show the cursor again.
+///
+/// Does nothing if the terminal was already restored. Only uses
+/// async-signal-safe calls, so it can run in a signal handler.
+pub fn restore() {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    if let Some(termios) = ORIGINAL.get() {
+        unsafe { libc::tcsetattr(STDOUT, libc::TCSANOW, termios) };
+    }
+    let _ = unistd::write(STDOUT, RESET);
+}
@@ -0,0 +1,450 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+const COLOR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "light-black",
+    "light-red",
+    "light-green",
+    "light-yellow",
+    "light-blue",
+    "light-magenta",
+    "light-cyan",
+    "light-white",
+];
+
+/// The basic colors as xterm shows them, used to pick the closest one.
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Colors the terminal can show.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColorDepth {
+    /// Guess from `COLORTERM` and `TERM`.
+    Auto,
+    Basic,
+    Palette,
+    TrueColor,
+}
+
+impl ColorDepth {
+    fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Palette
+        } else {
+            Self::Basic
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorDepth {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "auto" => Ok(Self::Auto),
+            "16" => Ok(Self::Basic),
+            "256" => Ok(Self::Palette),
+            "truecolor" => Ok(Self::TrueColor),
+            other => Err(de::Error::custom(format!(
+                "unknown color depth `{}`, expected `auto`, `16`, `256` or `truecolor`",
+                other
+            ))),
+        }
+    }
+}
+
+/// A color by name, 256-color palette index or `#rrggbb` value.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Color {
+    /// The terminal's default color.
+    Reset,
+    /// One of the 16 named colors.
+    Basic(u8),
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn rgb(self) -> Option<(u8, u8, u8)> {
+        match self {
+            Self::Reset => None,
+            Self::Basic(n) => Some(BASIC_RGB[n as usize]),
+            Self::Indexed(n) if n < 16 => Some(BASIC_RGB[n as usize]),
+            Self::Indexed(n) if n < 232 => {
+                let n = n - 16;
+                let level = |v: u8| CUBE_LEVELS[v as usize];
+                Some((level(n / 36), level(n / 6 % 6), level(n % 6)))
+            }
+            Self::Indexed(n) => {
+                let gray = 8 + (n - 232) * 10;
+                Some((gray, gray, gray))
+            }
+            Self::Rgb(r, g, b) => Some((r, g, b)),
+        }
+    }
+
+    /// The closest color `depth` can show.
+    fn fit(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (Self::Reset, _) | (Self::Basic(_), _) => self,
+            (Self::Indexed(n), _) if n < 16 => Self::Basic(n),
+            (Self::Indexed(_), ColorDepth::Basic) | (Self::Rgb(..), ColorDepth::Basic) => {
+                let (r, g, b) = self.rgb().expect("color has no rgb value");
+                let distance = |&(br, bg, bb): &(u8, u8, u8)| {
+                    let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+                    d(r, br) + d(g, bg) + d(b, bb)
+                };
+                let (idx, _) = BASIC_RGB
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, rgb)| distance(rgb))
+                    .expect("no basic colors");
+                Self::Basic(idx as u8)
+            }
+            (Self::Rgb(r, g, b), ColorDepth::Palette) => {
+                let level = |v: u8| match v {
+                    0..=47 => 0,
+                    48..=114 => 1,
+                    _ => (v - 35) / 40,
+                };
+                Self::Indexed(16 + 36 * level(r) + 6 * level(g) + level(b))
+            }
+            _ => self,
+        }
+    }
+
+    fn write_sgr(self, f: &mut fmt::Formatter, background: bool) -> fmt::Result {
+        let offset = if background { 10 } else { 0 };
+        let extended = if background { 48 } else { 38 };
+        match self {
+            Self::Reset => write!(f, "\x1B[{}m", 39 + offset),
+            Self::Basic(n) if n < 8 => write!(f, "\x1B[{}m", 30 + offset + n),
+            Self::Basic(n) => write!(f, "\x1B[{}m", 90 + offset + n - 8),
+            Self::Indexed(n) => write!(f, "\x1B[{};5;{}m", extended, n),
+            Self::Rgb(r, g, b) => write!(f, "\x1B[{};2;{};{};{}m", extended, r, g, b),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if name == "default" {
+            return Ok(Self::Reset);
+        }
+        if let Some(idx) = COLOR_NAMES.iter().position(|n| *n == name) {
+            return Ok(Self::Basic(idx as u8));
+        }
+        if let Ok(idx) = name.parse() {
+            return Ok(Self::Indexed(idx));
+        }
+        if let Some(hex) = name.strip_prefix('#').filter(|hex| hex.len() == 6) {
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            if let (Some(r), Some(g), Some(b)) = (channel(0), channel(2), channel(4)) {
+                return Ok(Self::Rgb(r, g, b));
+            }
+        }
+
+        Err(format!("unknown color `{}`", name))
+    }
+}
+
+/// Colors and attributes of a piece of text.
+///
+/// Written like `bold light-cyan on 236`: attributes, the text color and
+/// the background color after `on`, each of them optional.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    invert: bool,
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut style = Self::default();
+        let mut words = text.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "plain" => {}
+                "bold" => style.bold = true,
+                "dim" => style.dim = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                "invert" => style.invert = true,
+                "on" => {
+                    let color = words.next().ok_or("missing color after `on`")?;
+                    style.bg = Some(color.parse()?);
+                }
+                color => style.fg = Some(color.parse()?),
+            }
+        }
+
+        Ok(style)
+    }
+}
+
+impl<'de> Deserialize<'de> for Style {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// What a piece of text is used for, each role has its own style.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Role {
+    Background,
+    Border,
+    BorderFocused,
+    Title,
+    Label,
+    LabelFocused,
+    Input,
+    InputFocused,
+    InputMasked,
+    /// Completion hints and scroll markers.
+    Hint,
+    /// Selected entries and buttons.
+    Selection,
+    Error,
+    Info,
+    Status,
+    /// Key names in the power action bar.
+    Keys,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Theme {
+    colors: ColorDepth,
+    background: Style,
+    border: Style,
+    border_focused: Style,
+    title: Style,
+    label: Style,
+    label_focused: Style,
+    input: Style,
+    input_focused: Style,
+    input_masked: Style,
+    hint: Style,
+    selection: Style,
+    error: Style,
+    info: Style,
+    status: Style,
+    keys: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let style = |text: &str| text.parse().expect("invalid default style");
+        Self {
+            colors: ColorDepth::Auto,
+            background: Style::default(),
+            border: Style::default(),
+            border_focused: Style::default(),
+            title: style("bold"),
+            label: Style::default(),
+            label_focused: style("bold"),
+            input: Style::default(),
+            input_focused: style("bold"),
+            input_masked: style("bold"),
+            hint: style("light-black"),
+            selection: style("invert"),
+            error: style("bold red"),
+            info: style("bold"),
+            status: style("yellow"),
+            keys: style("green"),
+        }
+    }
+}
+
+impl Theme {
+    /// Settle on a color depth if it was left to `auto`.
+    pub fn detect_colors(&mut self) {
+        if self.colors == ColorDepth::Auto {
+            self.colors = ColorDepth::detect();
+        }
+    }
+
+    pub fn style(&self, role: Role) -> Style {
+        match role {
+            Role::Background => self.background,
+            Role::Border => self.border,
+            Role::BorderFocused => self.border_focused,
+            Role::Title => self.title,
+            Role::Label => self.label,
+            Role::LabelFocused => self.label_focused,
+            Role::Input => self.input,
+            Role::InputFocused => self.input_focused,
+            Role::InputMasked => self.input_masked,
+            Role::Hint => self.hint,
+            Role::Selection => self.selection,
+            Role::Error => self.error,
+            Role::Info => self.info,
+            Role::Status => self.status,
+            Role::Keys => self.keys,
+        }
+    }
+
+    /// Switch to the style of `role`, drawn on top of the background.
+    pub fn paint(&self, role: Role) -> Paint<'_> {
+        Paint {
+            theme: self,
+            style: self.style(role),
+        }
+    }
+
+    /// Switch back to the plain background.
+    pub fn plain(&self) -> Paint<'_> {
+        self.paint(Role::Background)
+    }
+}
+
+/// Escape sequence selecting a style, see `Theme::paint`.
+pub struct Paint<'a> {
+    theme: &'a Theme,
+    style: Style,
+}
+
+impl fmt::Display for Paint<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (style, base) = (&self.style, &self.theme.background);
+        let depth = self.theme.colors;
+
+        f.write_str("\x1B[0m")?;
+        let attributes = [
+            (style.bold, 1),
+            (style.dim, 2),
+            (style.italic, 3),
+            (style.underline, 4),
+            (style.invert, 7),
+        ];
+        for &(_, code) in attributes.iter().filter(|(set, _)| *set) {
+            write!(f, "\x1B[{}m", code)?;
+        }
+        if let Some(fg) = style.fg.or(base.fg) {
+            fg.fit(depth).write_sgr(f, false)?;
+        }
+        if let Some(bg) = style.bg.or(base.bg) {
+            bg.fit(depth).write_sgr(f, true)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!("red".parse(), Ok(Color::Basic(1)));
+        assert_eq!("light-black".parse(), Ok(Color::Basic(8)));
+        assert_eq!("default".parse(), Ok(Color::Reset));
+        assert_eq!("208".parse(), Ok(Color::Indexed(208)));
+        assert_eq!("#ff8700".parse(), Ok(Color::Rgb(255, 135, 0)));
+        assert!("#ff87".parse::<Color>().is_err());
+        assert!("256".parse::<Color>().is_err());
+        assert!("orange".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn parses_styles() {
+        let style: Style = "bold underline cyan on #000080".parse().unwrap();
+        assert_eq!(
+            style,
+            Style {
+                fg: Some(Color::Basic(6)),
+                bg: Some(Color::Rgb(0, 0, 128)),
+                bold: true,
+                underline: true,
+                ..Style::default()
+            }
+        );
+        assert_eq!("plain".parse(), Ok(Style::default()));
+        assert!("bold on".parse::<Style>().is_err());
+    }
+
+    #[test]
+    fn fits_colors_to_depth() {
+        let orange = Color::Rgb(255, 135, 0);
+        assert_eq!(orange.fit(ColorDepth::TrueColor), orange);
+        assert_eq!(orange.fit(ColorDepth::Palette), Color::Indexed(208));
+        assert_eq!(orange.fit(ColorDepth::Basic), Color::Basic(3));
+        assert_eq!(Color::Indexed(196).fit(ColorDepth::Basic), Color::Basic(9));
+        assert_eq!(Color::Indexed(4).fit(ColorDepth::Basic), Color::Basic(4));
+        assert_eq!(Color::Indexed(244).fit(ColorDepth::Basic), Color::Basic(8));
+    }
+
+    #[test]
+    fn paints_on_background() {
+        let mut theme: Theme = toml::from_str(
+            r#"
+            colors = "256"
+            background = "white on blue"
+            error = "bold #ff0000"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(theme.plain().to_string(), "\x1B[0m\x1B[37m\x1B[44m");
+        assert_eq!(
+            theme.paint(Role::Error).to_string(),
+            "\x1B[0m\x1B[1m\x1B[38;5;196m\x1B[44m"
+        );
+
+        theme.colors = ColorDepth::Basic;
+        assert_eq!(
+            theme.paint(Role::Error).to_string(),
+            "\x1B[0m\x1B[1m\x1B[91m\x1B[44m"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+// This file is part auf the greetly package.
+//
+// (c) 2020 Bernd Busse
+//
+// For the full copyright and license information, please view the README.md file
+// that was distributed with this source code.
+//
+
+use std::fs;
+use std::path::Path;
+
+use crate::config::UserConfig;
+
+const DEFAULT_UID_MIN: u32 = 1000;
+const DEFAULT_UID_MAX: u32 = 60000;
+
+#[derive(Debug, Clone)]
+pub struct User {
+    pub name: String,
+    pub uid: u32,
+    pub real_name: Option<String>,
+    pub shell: String,
+}
+
+impl User {
+    /// Parse a single line of a passwd file.
+    fn parse(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split(':').collect();
+        if fields.len() != 7 || fields[0].is_empty() {
+            return None;
+        }
+
+        // The first comma separated field of the GECOS is the full name
+        let real_name = fields[4]
+            .split(',')
+            .next()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| name.replace('&', &capitalize(fields[0])));
+
+        Some(Self {
+            name: fields[0].to_owned(),
+            uid: fields[2].parse().ok()?,
+            real_name,
+            shell: fields[6].to_owned(),
+        })
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.real_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether the shell allows logging in, an empty one means `/bin/sh`.
+    fn has_login_shell(&self) -> bool {
+        let shell = Path::new(&self.shell)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        !matches!(shell.as_str(), "nologin" | "false")
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Read `UID_MIN` and `UID_MAX` from a login.defs file.
+fn read_uid_range(path: &Path) -> (Option<u32>, Option<u32>) {
+    match fs::read_to_string(path) {
+        Ok(content) => parse_uid_range(&content),
+        Err(_) => (None, None),
+    }
+}
+
+fn parse_uid_range(content: &str) -> (Option<u32>, Option<u32>) {
+    let mut range = (None, None);
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next().and_then(|v| v.parse().ok())) {
+            (Some("UID_MIN"), Some(value)) => range.0 = Some(value),
+            (Some("UID_MAX"), Some(value)) => range.1 = Some(value),
+            _ => {}
+        }
+    }
+
+    range
+}
+
+/// List the users allowed to log in, sorted by name.
+///
+/// Only users within the UID range, with a login shell and not in the
+/// configured hide list are returned. The UID range is taken from the
+/// config, then the login.defs file, then the usual defaults.
+pub fn load(config: &UserConfig) -> Vec<User> {
+    let (defs_min, defs_max) = read_uid_range(&config.login_defs);
+    let uid_min = config.uid_min.or(defs_min).unwrap_or(DEFAULT_UID_MIN);
+    let uid_max = config.uid_max.or(defs_max).unwrap_or(DEFAULT_UID_MAX);
+
+    let content = match fs::read_to_string(&config.passwd_file) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut users: Vec<User> = content
+        .lines()
+        .filter_map(User::parse)
+        .filter(|user| user.uid >= uid_min && user.uid <= uid_max)
+        .filter(|user| user.has_login_shell())
+        .filter(|user| !config.hidden.contains(&user.name))
+        .collect();
+    users.sort_by(|a, b| a.name.cmp(&b.name));
+    users.dedup_by(|a, b| a.name == b.name);
+
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::path::PathBuf;
+
+    #[test]
+    fn parses_passwd_lines() {
+        let user =
+            User::parse("alice:x:1000:1000:Alice Liddell,Room 1,,:/home/alice:/bin/zsh").unwrap();
+        assert_eq!(user.name, "alice");
+        assert_eq!(user.uid, 1000);
+        assert_eq!(user.display_name(), "Alice Liddell");
+        assert_eq!(user.shell, "/bin/zsh");
+
+        let user = User::parse("bob:x:1001:1001::/home/bob:").unwrap();
+        assert_eq!(user.real_name, None);
+        assert_eq!(user.display_name(), "bob");
+
+        assert!(User::parse("").is_none());
+        assert!(User::parse(":x:1002:1002::/:/bin/sh").is_none());
+        assert!(User::parse("carol:x:many:1003::/:/bin/sh").is_none());
+        assert!(User::parse("dave:x:1004:1004::/home/dave").is_none());
+    }
+
+    #[test]
+    fn expands_ampersand_in_gecos() {
+        let user = User::parse("eve:x:1005:1005:& Example,,,:/home/eve:/bin/sh").unwrap();
+        assert_eq!(user.display_name(), "Eve Example");
+        assert_eq!(capitalize("ärger"), "Ärger");
+        assert_eq!(capitalize(""), "");
+    }
+
+    #[test]
+    fn checks_login_shell() {
+        let shell = |shell: &str| {
+            User::parse(&format!("u:x:1000:1000::/home/u:{}", shell))
+                .unwrap()
+                .has_login_shell()
+        };
+        assert!(shell("/bin/bash"));
+        // An empty shell means /bin/sh
+        assert!(shell(""));
+        assert!(!shell("/usr/sbin/nologin"));
+        assert!(!shell("/bin/false"));
+    }
+
+    #[test]
+    fn parses_uid_range() {
+        let defs = "# UID_MIN 1\nUID_MIN\t 500\nUID_MAX   abc\nMAIL_DIR /var/mail\n";
+        assert_eq!(parse_uid_range(defs), (Some(500), None));
+        assert_eq!(parse_uid_range("UID_MAX 2000"), (None, Some(2000)));
+        assert_eq!(
+            read_uid_range(Path::new("/nonexistent/login.defs")),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn loads_users_allowed_to_log_in() {
+        let dir = env::temp_dir().join(format!("greetly-test-{}-users", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let passwd = [
+            "root:x:0:0:root:/root:/bin/bash",
+            "zoe:x:1002:1002::/home/zoe:",
+            "alice:x:1000:1000:Alice:/home/alice:/bin/bash",
+            "daemon:x:1001:1001::/:/usr/sbin/nologin",
+            "hidden:x:1003:1003::/:/bin/bash",
+            "big:x:70000:70000::/:/bin/bash",
+            "alice:x:1004:1004:Duplicate:/home/alice:/bin/bash",
+        ];
+        fs::write(dir.join("passwd"), passwd.join("\n")).unwrap();
+        fs::write(dir.join("login.defs"), "UID_MAX 60000\n").unwrap();
+
+        let config = UserConfig {
+            passwd_file: dir.join("passwd"),
+            login_defs: dir.join("login.defs"),
+            hidden: vec!["hidden".to_owned()],
+            ..UserConfig::default()
+        };
+        let names: Vec<_> = load(&config).into_iter().map(|user| user.name).collect();
+        assert_eq!(names, ["alice", "zoe"]);
+
+        let config = UserConfig {
+            passwd_file: PathBuf::from("/nonexistent/passwd"),
+            ..config
+        };
+        assert!(load(&config).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}